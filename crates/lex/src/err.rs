@@ -1,5 +1,4 @@
-use crate::token::{SourceLocation, SourceSpan, Token};
-use crate::tokenizer::{TokenStream};
+use crate::{SourceLocation, SourceSpan, Token, TokenStream};
 
 #[derive(Debug, Clone, Copy)]
 pub struct NonTokenizableSubstringError {
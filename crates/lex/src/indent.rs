@@ -1,18 +1,48 @@
-use crate::token::{Token, TokenKind};
+use std::fmt::Display;
 
-pub fn indented_tokens<'a>(mut iterator: impl Iterator<Item = Token>) -> Vec<Token> {
+use crate::token::{SourceLocation, Token, TokenKind};
+
+/// Indentation that opens no block, blocks only ever go one level deeper than the line
+/// introducing them.
+#[derive(Debug, Clone, Copy)]
+pub enum IndentationError {
+    /// The first line is indented, there is no block it could be in.
+    IndentedFirstLine { loc: SourceLocation },
+    /// A line is indented more than one level deeper than the line before it.
+    TooDeep { loc: SourceLocation },
+}
+
+impl IndentationError {
+    pub fn loc(&self) -> SourceLocation {
+        match self {
+            IndentationError::IndentedFirstLine { loc } | IndentationError::TooDeep { loc } => *loc,
+        }
+    }
+}
+
+impl Display for IndentationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndentationError::IndentedFirstLine { .. } => write!(f, "unexpected indentation"),
+            IndentationError::TooDeep { .. } => write!(f, "indentation deeper than one level"),
+        }
+    }
+}
+
+pub fn indented_tokens(
+    mut iterator: impl Iterator<Item = Token>,
+) -> Result<Vec<Token>, IndentationError> {
     let mut prev = match iterator.next() {
         Some(x) => x,
-        None => return vec![],
+        None => return Ok(vec![]),
     };
 
-    assert!(
-        std::mem::discriminant(&prev.kind) != std::mem::discriminant(&TokenKind::Whitespace(0xF00))
-    );
+    if let TokenKind::Whitespace(_) = prev.kind {
+        return Err(IndentationError::IndentedFirstLine { loc: prev.loc });
+    }
 
     let mut indentation = 0;
 
-    // XXX: handle the case when file starts with whitespaces
     let mut out = vec![];
     loop {
         let next = iterator.next();
@@ -54,7 +84,7 @@ pub fn indented_tokens<'a>(mut iterator: impl Iterator<Item = Token>) -> Vec<Tok
                     }
                     indentation = indent_spaces;
                 } else if indent_spaces > indentation {
-                    todo!()
+                    return Err(IndentationError::TooDeep { loc });
                 }
 
                 if let Some(next) = iterator.next() {
@@ -111,6 +141,33 @@ pub fn indented_tokens<'a>(mut iterator: impl Iterator<Item = Token>) -> Vec<Tok
                 break;
             }
 
+            // the last line lacks a newline, close it as if it had one
+            (
+                tok,
+                Some(
+                    end @ Token {
+                        kind: TokenKind::End,
+                        ..
+                    },
+                ),
+            ) => {
+                out.push(tok);
+                out.push(Token {
+                    kind: TokenKind::Newline,
+                    span: (end.span.0, end.span.0),
+                    loc: end.loc,
+                });
+                for _ in 0..indentation {
+                    out.push(Token {
+                        kind: TokenKind::Dendent,
+                        span: end.span,
+                        loc: end.loc,
+                    });
+                }
+                out.push(end);
+                break;
+            }
+
             (last_tok, None) => {
                 out.push(last_tok);
                 break;
@@ -125,5 +182,5 @@ pub fn indented_tokens<'a>(mut iterator: impl Iterator<Item = Token>) -> Vec<Tok
         }
     }
 
-    out
+    Ok(out)
 }
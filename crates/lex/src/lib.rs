@@ -1,3 +1,6 @@
+// lets the `::lex::...` paths emitted by `#[derive(SourceObject)]` resolve in here too
+extern crate self as lex;
+
 mod indent;
 mod rules;
 mod token;
@@ -9,6 +12,53 @@ pub use rules::*;
 pub use token::*;
 pub use tokenizer::*;
 
+/// Turns physical lines into logical ones: comments are dropped along with the lines left
+/// holding nothing but whitespace, and line breaks inside of brackets are joined, so that
+/// none of them can disturb the indentation tracking of [`indented_tokens`].
+pub fn logical_lines(input: impl IntoIterator<Item = Token>) -> Vec<Token> {
+    let mut out = vec![];
+    let mut line = vec![];
+    let mut depth = 0usize;
+    for token in input {
+        match token.kind {
+            TokenKind::LeftParenthese | TokenKind::LeftBracket | TokenKind::LeftBrace => depth += 1,
+            TokenKind::RightParenthese | TokenKind::RightBracket | TokenKind::RightBrace => {
+                depth = depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+
+        let is_newline = matches!(token.kind, TokenKind::Newline);
+        if is_newline && depth > 0 {
+            continue;
+        }
+        if !matches!(token.kind, TokenKind::Comment(_)) {
+            line.push(token);
+        }
+
+        if is_newline {
+            let blank = line
+                .iter()
+                .all(|token| matches!(token.kind, TokenKind::Whitespace(_) | TokenKind::Newline));
+            if !blank {
+                out.append(&mut line);
+            }
+            line.clear();
+        }
+    }
+
+    // a last line without a newline keeps its end of input marker, even when blank, and its
+    // indentation unless it is blank
+    let blank = line
+        .iter()
+        .all(|token| matches!(token.kind, TokenKind::Whitespace(_) | TokenKind::End));
+    out.extend(
+        line.into_iter()
+            .filter(|token| !blank || !matches!(token.kind, TokenKind::Whitespace(_))),
+    );
+    out
+}
+
 pub fn omitted_spaces(input: impl IntoIterator<Item = Token>) -> impl IntoIterator<Item = Token> {
     input
         .into_iter()
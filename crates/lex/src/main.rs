@@ -1,14 +1,9 @@
 use std::fs;
 
 use err::{ErrorAwareTokenStream, TokenizerResult, NonTokenizableSubstringError};
-use indent::indented_tokens;
-use token::Token;
+use lex::{indented_tokens, SourceLocation, SourceSpan, Token, TokenStream};
 
 mod err;
-mod indent;
-mod rules;
-mod token;
-mod tokenizer;
 
 fn main() {
     let contents = fs::read_to_string("sample.wb").expect("Should have been able to read the file");
@@ -16,7 +11,7 @@ fn main() {
         ErrorAwareTokenStream::new(&contents.as_str()).collect();
     let tokens: Result<Vec<Token>, NonTokenizableSubstringError> = tokens.into_iter().collect();
     let tokens = tokens.unwrap();
-    let indented_toks = indented_tokens(tokens.into_iter());
+    let indented_toks = indented_tokens(tokens.into_iter()).unwrap();
 
     for tok in indented_toks {
         println!("{tok:?}");
@@ -1,8 +1,9 @@
 use std::fmt::Debug;
 
 use r#macro::SourceObject;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct SourceLocation {
     pub index: usize,
     pub col: u32,
@@ -45,18 +46,110 @@ impl Default for SourceLocation {
 
 pub type SourceSpan = (usize, usize);
 
+/// Anything with a place in the source. Derived for structs from their `loc` and `span`
+/// fields, which can be renamed, and for enums whose variants each hold such a node:
+///
+/// ```
+/// use lex::{SourceLocation, SourceObject, SourceSpan};
+///
+/// #[derive(r#macro::SourceObject)]
+/// #[source(loc = "start", span = "extent")]
+/// struct Name {
+///     start: SourceLocation,
+///     extent: SourceSpan,
+/// }
+///
+/// #[derive(r#macro::SourceObject)]
+/// struct Wrapped<T> {
+///     loc: SourceLocation,
+///     span: SourceSpan,
+///     inner: T,
+/// }
+///
+/// #[derive(r#macro::SourceObject)]
+/// enum Node<T: SourceObject> {
+///     Name(Name),
+///     Wrapped { node: Wrapped<T> },
+///     Other(T),
+/// }
+///
+/// let name = Name { start: SourceLocation::default(), extent: (3, 7) };
+/// assert_eq!(Node::<Name>::Name(name).source_span(), (3, 7));
+/// ```
+///
+/// A struct without the fields does not derive it:
+///
+/// ```compile_fail
+/// #[derive(r#macro::SourceObject)]
+/// struct Name {
+///     start: lex::SourceLocation,
+///     span: lex::SourceSpan,
+/// }
+/// ```
+///
+/// Nor does one with a misspelled key:
+///
+/// ```compile_fail
+/// #[derive(r#macro::SourceObject)]
+/// #[source(location = "start")]
+/// struct Name {
+///     start: lex::SourceLocation,
+///     span: lex::SourceSpan,
+/// }
+/// ```
+///
+/// Nor an enum with a variant holding anything but a single node:
+///
+/// ```compile_fail
+/// #[derive(r#macro::SourceObject)]
+/// enum Node {
+///     Pair(lex::Token, lex::Token),
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[derive(r#macro::SourceObject)]
+/// enum Node {
+///     Text(String),
+/// }
+/// ```
 pub trait SourceObject {
     fn source_location(&self) -> SourceLocation;
     fn source_span(&self) -> SourceSpan;
 }
 
+impl<T: SourceObject + ?Sized> SourceObject for &T {
+    fn source_location(&self) -> SourceLocation {
+        (**self).source_location()
+    }
+
+    fn source_span(&self) -> SourceSpan {
+        (**self).source_span()
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Keyword {
     Let,
     While,
+    If,
+    Elif,
+    Else,
+    Fn,
+    Return,
+    For,
+    In,
+    Break,
+    Continue,
+    Struct,
+    Enum,
+    Match,
+    Import,
+    From,
+    Pub,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum Operator {
     Equals,
     Add,
@@ -66,6 +159,8 @@ pub enum Operator {
     Mod,
     Greater,
     Less,
+    And,
+    Or,
 }
 
 impl Into<&str> for Operator {
@@ -81,6 +176,8 @@ impl Into<&str> for Operator {
             Greater => ">",
             Less => "<",
             Equals => "=",
+            And => "and",
+            Or => "or",
         }
     }
 }
@@ -98,6 +195,8 @@ impl TryFrom<&str> for Operator {
             ">" => Ok(Operator::Greater),
             "<" => Ok(Operator::Less),
             "=" => Ok(Operator::Equals),
+            "and" => Ok(Operator::And),
+            "or" => Ok(Operator::Or),
             _ => Err(())
         }
     }
@@ -107,6 +206,7 @@ impl TryFrom<&str> for Operator {
 pub enum Punctuation {
     Colon,
     Semicolon,
+    Comma,
 }
 
 #[derive(Debug, PartialEq)]
@@ -117,15 +217,28 @@ pub enum TokenKind {
     CompoundOperator(Operator),
     Punctuation(Punctuation),
     Whitespace(u32),
+    Comment(String),
     Indent,
     Dendent,
     Newline,
     LeftParenthese,
     RightParenthese,
+    Backslash,
+    Arrow,
+    DoubleDot,
+    Dot,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Pipe,
     Integer(String),
     End,
 }
 
+// syntax trees hold these as they are, without anything below to visit
+traverse::leaves!(SourceLocation, Operator);
+
 #[derive(Debug, SourceObject)]
 pub struct Token {
     pub loc: SourceLocation,
@@ -15,7 +15,7 @@ pub struct TokenStream<'a> {
 pub fn default_tokenizer_rules() -> Vec<Box<dyn TokenizerRule>> {
     vec![
         RegexTokenizerRule::new_box(
-            Regex::new(r"^(let|while)").unwrap(),
+            Regex::new(r"^(let|while|if|elif|else|fn|return|for|in|break|continue|struct|enum|match|import|from|pub)\b").unwrap(),
             Box::new(|captured: &str, span, loc| {
                 use Keyword::*;
                 Token {
@@ -24,13 +24,36 @@ pub fn default_tokenizer_rules() -> Vec<Box<dyn TokenizerRule>> {
                     kind: TokenKind::Keyword(match captured {
                         "let" => Let,
                         "while" => While,
+                        "if" => If,
+                        "elif" => Elif,
+                        "else" => Else,
+                        "fn" => Fn,
+                        "return" => Return,
+                        "for" => For,
+                        "in" => In,
+                        "break" => Break,
+                        "continue" => Continue,
+                        "struct" => Struct,
+                        "enum" => Enum,
+                        "match" => Match,
+                        "import" => Import,
+                        "from" => From,
+                        "pub" => Pub,
                         _ => unreachable!(),
                     }),
                 }
             }),
         ),
         RegexTokenizerRule::new_box(
-            Regex::new(r"^[0-9_]+").unwrap(),
+            Regex::new(r"^(and|or)\b").unwrap(),
+            Box::new(|captured, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::Operator(captured.try_into().unwrap()),
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^[0-9][0-9_]*").unwrap(),
             Box::new(|captured, span, loc| Token {
                 loc,
                 span,
@@ -45,6 +68,14 @@ pub fn default_tokenizer_rules() -> Vec<Box<dyn TokenizerRule>> {
                 kind: TokenKind::Whitespace(captured.len() as u32),
             }),
         ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^#[^\n\r]*").unwrap(),
+            Box::new(|captured, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::Comment(captured.to_string()),
+            }),
+        ),
         RegexTokenizerRule::new_box(
             Regex::new(r"^[\n\r]").unwrap(),
             Box::new(|_, span, loc| Token {
@@ -69,6 +100,46 @@ pub fn default_tokenizer_rules() -> Vec<Box<dyn TokenizerRule>> {
                 kind: TokenKind::RightParenthese,
             }),
         ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\{").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::LeftBrace,
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\}").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::RightBrace,
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\[").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::LeftBracket,
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\]").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::RightBracket,
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\|").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::Pipe,
+            }),
+        ),
         RegexTokenizerRule::new_box(
             Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*").unwrap(),
             Box::new(|captured, span, loc| Token {
@@ -77,6 +148,38 @@ pub fn default_tokenizer_rules() -> Vec<Box<dyn TokenizerRule>> {
                 kind: TokenKind::Identifier(captured.to_string()),
             }),
         ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\\").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::Backslash,
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\.\.").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::DoubleDot,
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^\.").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::Dot,
+            }),
+        ),
+        RegexTokenizerRule::new_box(
+            Regex::new(r"^->").unwrap(),
+            Box::new(|_, span, loc| Token {
+                loc,
+                span,
+                kind: TokenKind::Arrow,
+            }),
+        ),
         RegexTokenizerRule::new_box(
             Regex::new(r"^[-\+\\*%><]=").unwrap(),
             Box::new(|captured, span, loc| Token {
@@ -96,13 +199,14 @@ pub fn default_tokenizer_rules() -> Vec<Box<dyn TokenizerRule>> {
             }),
         ),
         RegexTokenizerRule::new_box(
-            Regex::new(r"^[;:]").unwrap(),
+            Regex::new(r"^[;:,]").unwrap(),
             Box::new(|capture, span, loc| Token {
                 loc,
                 span,
                 kind: TokenKind::Punctuation(match capture {
                     ":" => Punctuation::Colon,
                     ";" => Punctuation::Semicolon,
+                    "," => Punctuation::Comma,
                     _ => unreachable!(),
                 }),
             }),
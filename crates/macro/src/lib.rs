@@ -1,25 +1,27 @@
+mod source_object;
+mod visitable;
+
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
-use quote::quote;
 
-#[proc_macro_derive(SourceObject)]
+/// Implements `lex::SourceObject`. Structs hand out their `loc` and `span` fields, or the
+/// ones named with `#[source(loc = "...", span = "...")]`; enums defer to the single node
+/// held by each variant.
+#[proc_macro_derive(SourceObject, attributes(source))]
 pub fn derive(input: TokenStream) -> TokenStream {
     let parsed = parse_macro_input!(input as DeriveInput);
-    impl_source_object(&parsed)
+    source_object::impl_source_object(&parsed).into()
 }
 
-fn impl_source_object(ast: &syn::DeriveInput) -> TokenStream {
-    let name = &ast.ident;
-    let gen = quote! {
-        impl SourceObject for #name {
-            fn source_location(&self) -> SourceLocation {
-                self.loc
-            }
-        
-            fn source_span(&self) -> SourceSpan {
-                self.span
-            }
-        }
-    };
-    gen.into()
+/// Implements `traverse::Visitable`, `VisitableMut` and `Foldable`, walking every field of
+/// the struct or variant, for the `Visit`, `VisitMut` and `Fold` visitors of the `visit`,
+/// `visit_mut` and `fold` modules of the crate root, or of the module given with
+/// `#[visit(visitors = "...")]`. Types marked `#[visit(name)]` are nodes, handed to
+/// `visit_name`, `visit_name_mut` and `fold_name` of the visitor instead of being walked
+/// directly. The root of the tree lists the other nodes with `#[visit(name, nodes(other =
+/// "Other", ...))]`, and the three modules are generated next to it.
+#[proc_macro_derive(Visitable, attributes(visit))]
+pub fn derive_visitable(input: TokenStream) -> TokenStream {
+    let parsed = parse_macro_input!(input as DeriveInput);
+    visitable::impl_visitable(&parsed).into()
 }
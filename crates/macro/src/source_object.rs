@@ -0,0 +1,148 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{
+    parse_quote, Attribute, Data, DataEnum, DataStruct, DeriveInput, Error, Fields, Generics, Lit,
+    Meta, NestedMeta, Result,
+};
+
+/// The fields a struct keeps its location and span in, `loc` and `span` unless renamed with
+/// `#[source(loc = "...", span = "...")]`.
+struct SourceFields {
+    loc: Ident,
+    span: Ident,
+}
+
+impl SourceFields {
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut fields = SourceFields {
+            loc: parse_quote!(loc),
+            span: parse_quote!(span),
+        };
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("source")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new_spanned(meta, "expected `#[source(...)]`")),
+            };
+            for nested in list.nested {
+                let (path, lit) = match &nested {
+                    NestedMeta::Meta(Meta::NameValue(pair)) => (&pair.path, &pair.lit),
+                    _ => {
+                        let message = "expected `loc = \"...\"` or `span = \"...\"`";
+                        return Err(Error::new_spanned(nested, message));
+                    }
+                };
+                let field = match lit {
+                    Lit::Str(name) => name.parse::<Ident>()?,
+                    _ => return Err(Error::new_spanned(lit, "expected the field name as a string")),
+                };
+                if path.is_ident("loc") {
+                    fields.loc = field;
+                } else if path.is_ident("span") {
+                    fields.span = field;
+                } else {
+                    return Err(Error::new_spanned(path, "unknown key, expected `loc` or `span`"));
+                }
+            }
+        }
+        Ok(fields)
+    }
+}
+
+fn struct_bodies(ast: &DeriveInput, data: &DataStruct) -> Result<(TokenStream, TokenStream)> {
+    let SourceFields { loc, span } = SourceFields::from_attrs(&ast.attrs)?;
+    let named: Vec<&Ident> = match &data.fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .filter_map(|field| field.ident.as_ref())
+            .collect(),
+        _ => {
+            let message = "`SourceObject` needs a struct with named fields";
+            return Err(Error::new_spanned(&ast.ident, message));
+        }
+    };
+    for field in [&loc, &span] {
+        if !named.contains(&field) {
+            let message = format!(
+                "no field `{field}`, name the location and span fields with \
+                 `#[source(loc = \"...\", span = \"...\")]`"
+            );
+            return Err(Error::new_spanned(&ast.ident, message));
+        }
+    }
+    Ok((quote! { self.#loc }, quote! { self.#span }))
+}
+
+fn enum_bodies(
+    ast: &DeriveInput,
+    data: &DataEnum,
+    generics: &mut Generics,
+) -> Result<(TokenStream, TokenStream)> {
+    if let Some(attr) = ast.attrs.iter().find(|attr| attr.path.is_ident("source")) {
+        return Err(Error::new_spanned(attr, "`#[source(...)]` only applies to structs"));
+    }
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(&ast.ident, "`SourceObject` needs an enum with variants"));
+    }
+
+    // every variant holds exactly one node, which knows where it is
+    let mut arms = vec![];
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let field = match variant.fields.iter().collect::<Vec<_>>()[..] {
+            [field] => field,
+            _ => {
+                return Err(Error::new_spanned(
+                    variant,
+                    "`SourceObject` variants must hold exactly one node to defer to",
+                ))
+            }
+        };
+        let pattern = match &field.ident {
+            Some(name) => quote! { Self::#ident { #name: node } },
+            None => quote! { Self::#ident(node) },
+        };
+        arms.push(pattern);
+
+        let ty = &field.ty;
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#ty: ::lex::SourceObject));
+    }
+
+    Ok((
+        quote! { match self { #(#arms => ::lex::SourceObject::source_location(node),)* } },
+        quote! { match self { #(#arms => ::lex::SourceObject::source_span(node),)* } },
+    ))
+}
+
+pub fn impl_source_object(ast: &DeriveInput) -> TokenStream {
+    let mut generics = ast.generics.clone();
+    let bodies = match &ast.data {
+        Data::Struct(data) => struct_bodies(ast, data),
+        Data::Enum(data) => enum_bodies(ast, data, &mut generics),
+        Data::Union(data) => Err(Error::new_spanned(
+            data.union_token,
+            "`SourceObject` cannot be derived for unions",
+        )),
+    };
+    let (location, span) = match bodies {
+        Ok(bodies) => bodies,
+        Err(error) => return error.to_compile_error(),
+    };
+
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::lex::SourceObject for #name #ty_generics #where_clause {
+            fn source_location(&self) -> ::lex::SourceLocation {
+                #location
+            }
+
+            fn source_span(&self) -> ::lex::SourceSpan {
+                #span
+            }
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Error, Fields, GenericParam, Lit, LitStr, Meta,
+    NestedMeta, Path, Result, Type,
+};
+
+/// What `#[visit(...)]` says about a type: the visitor method suffix of the types that are
+/// nodes of their own rather than mere parts of one, the module holding the `visit`,
+/// `visit_mut` and `fold` modules, `crate` unless given as `visitors = "..."`, and on the
+/// root of the tree, the other nodes as `nodes(name = "Type", ...)`.
+struct VisitAttrs {
+    method: Option<Ident>,
+    visitors: Path,
+    nodes: Vec<(Ident, Type)>,
+}
+
+fn lit_str(lit: &Lit) -> Result<&LitStr> {
+    match lit {
+        Lit::Str(string) => Ok(string),
+        _ => Err(Error::new_spanned(lit, "expected a string")),
+    }
+}
+
+impl VisitAttrs {
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut visit = VisitAttrs {
+            method: None,
+            visitors: parse_quote!(crate),
+            nodes: vec![],
+        };
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("visit")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(Error::new_spanned(meta, "expected `#[visit(...)]`")),
+            };
+            for nested in list.nested {
+                match &nested {
+                    NestedMeta::Meta(Meta::Path(path)) => {
+                        let method = path
+                            .get_ident()
+                            .ok_or_else(|| Error::new_spanned(path, "expected a method name"))?;
+                        if visit.method.is_some() {
+                            return Err(Error::new_spanned(path, "a node has a single method"));
+                        }
+                        visit.method = Some(method.to_owned());
+                    }
+                    NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("visitors") => {
+                        visit.visitors = lit_str(&pair.lit)?.parse()?;
+                    }
+                    NestedMeta::Meta(Meta::List(nodes)) if nodes.path.is_ident("nodes") => {
+                        for node in &nodes.nested {
+                            let pair = match node {
+                                NestedMeta::Meta(Meta::NameValue(pair)) => pair,
+                                _ => {
+                                    let message = "expected `name = \"Type\"`";
+                                    return Err(Error::new_spanned(node, message));
+                                }
+                            };
+                            let method = pair.path.get_ident().ok_or_else(|| {
+                                Error::new_spanned(&pair.path, "expected a method name")
+                            })?;
+                            visit.nodes.push((method.to_owned(), lit_str(&pair.lit)?.parse()?));
+                        }
+                    }
+                    _ => {
+                        let message = "unknown argument, expected a method name, \
+                                       `visitors = \"...\"` or `nodes(...)`";
+                        return Err(Error::new_spanned(nested, message));
+                    }
+                }
+            }
+        }
+        Ok(visit)
+    }
+}
+
+/// The names to bind the fields of a variant to, along with the pattern binding them.
+fn bindings(fields: &Fields) -> (Vec<Ident>, TokenStream) {
+    match fields {
+        Fields::Named(named) => {
+            let names: Vec<Ident> = named
+                .named
+                .iter()
+                .map(|field| field.ident.clone().unwrap())
+                .collect();
+            let pattern = quote! { { #(#names),* } };
+            (names, pattern)
+        }
+        Fields::Unnamed(unnamed) => {
+            let names: Vec<Ident> = (0..unnamed.unnamed.len())
+                .map(|i| Ident::new(&format!("field{i}"), Span::call_site()))
+                .collect();
+            let pattern = quote! { ( #(#names),* ) };
+            (names, pattern)
+        }
+        Fields::Unit => (vec![], quote! {}),
+    }
+}
+
+/// Rebuilds a variant or struct from its folded fields.
+fn rebuild(fields: &Fields, names: &[Ident]) -> TokenStream {
+    let folded = names
+        .iter()
+        .map(|name| quote! { ::traverse::Foldable::fold_with(#name, folder) });
+    match fields {
+        Fields::Named(_) => quote! { { #(#names: #folded),* } },
+        Fields::Unnamed(_) => quote! { ( #(#folded),* ) },
+        Fields::Unit => quote! {},
+    }
+}
+
+/// The generics of `ast` along with `params`, lifetimes going first.
+fn with_params(ast: &DeriveInput, params: &[GenericParam]) -> syn::Generics {
+    let mut generics = ast.generics.clone();
+    for param in params.iter().rev() {
+        let at = match param {
+            GenericParam::Lifetime(_) => 0,
+            _ => generics.params.len(),
+        };
+        generics.params.insert(at, param.to_owned());
+    }
+    generics
+}
+
+/// The `visit`, `visit_mut` and `fold` modules, with a visitor method and a `walk_*` function
+/// for each node of `nodes`, in order.
+fn visitors(nodes: &[(Ident, Type)]) -> TokenStream {
+    let visit_methods = nodes.iter().map(|(method, ty)| {
+        let visit = format_ident!("visit_{}", method);
+        let walk = format_ident!("walk_{}", method);
+        quote! {
+            fn #visit(&mut self, node: &'ast #ty) {
+                #walk(self, node)
+            }
+        }
+    });
+    let walks = nodes.iter().map(|(method, ty)| {
+        let walk = format_ident!("walk_{}", method);
+        quote! {
+            pub fn #walk<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast #ty) {
+                ::traverse::Visitable::walk(node, visitor)
+            }
+        }
+    });
+    let visit_mut_methods = nodes.iter().map(|(method, ty)| {
+        let visit = format_ident!("visit_{}_mut", method);
+        let walk = format_ident!("walk_{}_mut", method);
+        quote! {
+            fn #visit(&mut self, node: &mut #ty) {
+                #walk(self, node)
+            }
+        }
+    });
+    let walks_mut = nodes.iter().map(|(method, ty)| {
+        let walk = format_ident!("walk_{}_mut", method);
+        quote! {
+            pub fn #walk<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut #ty) {
+                ::traverse::VisitableMut::walk_mut(node, visitor)
+            }
+        }
+    });
+    let fold_methods = nodes.iter().map(|(method, ty)| {
+        let fold = format_ident!("fold_{}", method);
+        let walk = format_ident!("walk_{}", method);
+        quote! {
+            fn #fold(&mut self, node: #ty) -> #ty {
+                #walk(self, node)
+            }
+        }
+    });
+    let fold_walks = nodes.iter().map(|(method, ty)| {
+        let walk = format_ident!("walk_{}", method);
+        quote! {
+            pub fn #walk<F: Fold + ?Sized>(folder: &mut F, node: #ty) -> #ty {
+                ::traverse::Foldable::fold_children(node, folder)
+            }
+        }
+    });
+
+    quote! {
+        /// Read-only traversal of the tree. Implementors override the nodes they care about
+        /// and call the matching `walk_*` function wherever they want to keep descending.
+        pub mod visit {
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub trait Visit<'ast> {
+                #(#visit_methods)*
+            }
+
+            #(#walks)*
+        }
+
+        /// In-place traversal of the tree, the mutable counterpart of `visit`.
+        pub mod visit_mut {
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub trait VisitMut {
+                #(#visit_mut_methods)*
+            }
+
+            #(#walks_mut)*
+        }
+
+        /// Rebuilding traversal of the tree: every node is taken by value and replaced by
+        /// what its `fold_*` method returns, the default being the node with its children
+        /// folded.
+        pub mod fold {
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub trait Fold {
+                #(#fold_methods)*
+            }
+
+            #(#fold_walks)*
+        }
+    }
+}
+
+pub fn impl_visitable(ast: &DeriveInput) -> TokenStream {
+    let VisitAttrs { method, visitors: root, nodes } = match VisitAttrs::from_attrs(&ast.attrs) {
+        Ok(visit) => visit,
+        Err(error) => return error.to_compile_error(),
+    };
+    let name = &ast.ident;
+    let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
+    // the visitors have a method for every node of the tree they walk
+    let visit_generics = with_params(
+        ast,
+        &[
+            parse_quote!('ast),
+            parse_quote!(V: #root::visit::Visit<'ast> + ?Sized),
+        ],
+    );
+    let visit_mut_generics =
+        with_params(ast, &[parse_quote!(V: #root::visit_mut::VisitMut + ?Sized)]);
+    let fold_generics = with_params(ast, &[parse_quote!(F: #root::fold::Fold + ?Sized)]);
+    let (visit_impl_generics, _, _) = visit_generics.split_for_impl();
+    let (visit_mut_impl_generics, _, _) = visit_mut_generics.split_for_impl();
+    let (fold_impl_generics, _, _) = fold_generics.split_for_impl();
+
+    let (walk, walk_mut, fold_children) = match &ast.data {
+        Data::Struct(data) => {
+            let (names, pattern) = bindings(&data.fields);
+            let rebuilt = rebuild(&data.fields, &names);
+            (
+                quote! {
+                    let Self #pattern = self;
+                    #(::traverse::Visitable::accept(#names, visitor);)*
+                },
+                quote! {
+                    let Self #pattern = self;
+                    #(::traverse::VisitableMut::accept_mut(#names, visitor);)*
+                },
+                quote! {
+                    let Self #pattern = self;
+                    Self #rebuilt
+                },
+            )
+        }
+        Data::Enum(data) => {
+            let arms: Vec<_> = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let ident = &variant.ident;
+                    let (names, pattern) = bindings(&variant.fields);
+                    let rebuilt = rebuild(&variant.fields, &names);
+                    (
+                        quote! {
+                            Self::#ident #pattern => {
+                                #(::traverse::Visitable::accept(#names, visitor);)*
+                            }
+                        },
+                        quote! {
+                            Self::#ident #pattern => {
+                                #(::traverse::VisitableMut::accept_mut(#names, visitor);)*
+                            }
+                        },
+                        quote! { Self::#ident #pattern => Self::#ident #rebuilt, },
+                    )
+                })
+                .collect();
+            let walk = arms.iter().map(|(walk, _, _)| walk);
+            let walk_mut = arms.iter().map(|(_, walk_mut, _)| walk_mut);
+            let fold = arms.iter().map(|(_, _, fold)| fold);
+            (
+                quote! { match self { #(#walk)* } },
+                quote! { match self { #(#walk_mut)* } },
+                quote! { match self { #(#fold)* } },
+            )
+        }
+        Data::Union(data) => {
+            return Error::new_spanned(data.union_token, "`Visitable` cannot be derived for unions")
+                .to_compile_error()
+        }
+    };
+
+    // nodes go through their visitor method, which walks them unless overridden
+    let (accept, accept_mut, fold_with) = match &method {
+        Some(method) => {
+            let visit = format_ident!("visit_{}", method);
+            let visit_mut = format_ident!("visit_{}_mut", method);
+            let fold = format_ident!("fold_{}", method);
+            (
+                quote! {
+                    fn accept(&'ast self, visitor: &mut V) {
+                        visitor.#visit(self)
+                    }
+                },
+                quote! {
+                    fn accept_mut(&mut self, visitor: &mut V) {
+                        visitor.#visit_mut(self)
+                    }
+                },
+                quote! {
+                    fn fold_with(self, folder: &mut F) -> Self {
+                        folder.#fold(self)
+                    }
+                },
+            )
+        }
+        None => (quote! {}, quote! {}, quote! {}),
+    };
+
+    // the root of the tree brings the visitors, starting with its own method
+    let visitors = match (&method, nodes.is_empty()) {
+        (_, true) => quote! {},
+        (Some(method), false) if ast.generics.params.is_empty() => {
+            let nodes: Vec<_> = std::iter::once((method.to_owned(), parse_quote!(#name)))
+                .chain(nodes)
+                .collect();
+            visitors(&nodes)
+        }
+        (Some(_), false) => {
+            let message = "`nodes(...)` needs a root without generic parameters";
+            return Error::new_spanned(&ast.generics, message).to_compile_error();
+        }
+        (None, false) => {
+            let message = "`nodes(...)` needs the method name of the root as well";
+            return Error::new_spanned(name, message).to_compile_error();
+        }
+    };
+
+    quote! {
+        impl #visit_impl_generics ::traverse::Visitable<'ast, V> for #name #ty_generics #where_clause {
+            #accept
+
+            #[allow(unused_variables)]
+            fn walk(&'ast self, visitor: &mut V) {
+                #walk
+            }
+        }
+
+        impl #visit_mut_impl_generics ::traverse::VisitableMut<V> for #name #ty_generics #where_clause {
+            #accept_mut
+
+            #[allow(unused_variables)]
+            fn walk_mut(&mut self, visitor: &mut V) {
+                #walk_mut
+            }
+        }
+
+        impl #fold_impl_generics ::traverse::Foldable<F> for #name #ty_generics #where_clause {
+            #fold_with
+
+            #[allow(unused_variables)]
+            fn fold_children(self, folder: &mut F) -> Self {
+                #fold_children
+            }
+        }
+
+        #visitors
+    }
+}
@@ -0,0 +1,80 @@
+//! Lexes and parses synthetic programs of growing size, reporting the time taken and the
+//! memory allocated for the tree. Run with `cargo bench -p parse`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use lex::{indented_tokens, logical_lines, omitted_spaces, Token, TokenStream};
+use parse::Parser;
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// A program of `functions` functions, each with a few statements over long arithmetic.
+fn synthetic(functions: usize) -> String {
+    let mut source = String::new();
+    for i in 0..functions {
+        source += &format!("fn f{i}(a, b):\n");
+        source += "    let xs = [a, b, a * b + 1, (a - b) * 2]\n";
+        source += "    let t = (a + b * 3 - a % 2, xs[1])\n";
+        source += "    if a < b:\n";
+        source += "        return a * 2 + b * 3 + a * b - 4 * (a + b) + 1\n";
+        source += "    for x in 0..10:\n";
+        source += "        let c = a + x * (b - 1) * (a + 2)\n";
+        source += "    return f(a, b) + g(a) * h(b, a + 1)\n";
+    }
+    source
+}
+
+fn tokens(source: &str) -> Vec<Token> {
+    let lines = logical_lines(TokenStream::new(source));
+    omitted_spaces(indented_tokens(lines.into_iter()).unwrap())
+        .into_iter()
+        .collect()
+}
+
+fn main() {
+    println!(
+        "{:>9} {:>10} {:>10} {:>12} {:>12}",
+        "functions", "lex", "parse", "allocated", "allocations"
+    );
+    for functions in [100, 1_000, 5_000] {
+        let source = synthetic(functions);
+
+        let start = Instant::now();
+        let tokens = tokens(&source);
+        let lexed = start.elapsed();
+
+        let bytes = ALLOCATED.load(Ordering::Relaxed);
+        let count = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let block = Parser::new(tokens).parse_module().unwrap();
+        let parsed = start.elapsed();
+        let bytes = ALLOCATED.load(Ordering::Relaxed) - bytes;
+        let count = ALLOCATIONS.load(Ordering::Relaxed) - count;
+
+        assert_eq!(block.stmts.len(), functions);
+        let kilobytes = bytes / 1024;
+        println!("{functions:>9} {lexed:>10.2?} {parsed:>10.2?} {kilobytes:>10}KB {count:>12}");
+    }
+}
@@ -0,0 +1,58 @@
+//! Parses programs nesting parentheses, applications and blocks ever deeper, to check that
+//! backtracking stays linear in the depth. Run with `cargo bench -p parse --bench nesting`.
+
+use std::time::Instant;
+
+use lex::{indented_tokens, logical_lines, omitted_spaces, Token, TokenStream};
+use parse::Parser;
+
+/// Names a way of nesting and builds a program nesting that deep.
+type Shape = (&'static str, fn(usize) -> String);
+
+/// `let x = ((...(1)...))`
+fn parens(depth: usize) -> String {
+    format!("let x = {}1{}\n", "(".repeat(depth), ")".repeat(depth))
+}
+
+/// `let x = f (f (...(f 1)...))`
+fn applications(depth: usize) -> String {
+    format!("let x = {}1{}\n", "f (".repeat(depth), ")".repeat(depth))
+}
+
+/// `if` statements, each in the body of the one before.
+fn blocks(depth: usize) -> String {
+    let mut source = String::new();
+    for level in 0..depth {
+        source += &format!("{}if a < b:\n", "    ".repeat(level));
+    }
+    source += &format!("{}return a + b\n", "    ".repeat(depth));
+    source
+}
+
+fn tokens(source: &str) -> Vec<Token> {
+    let lines = logical_lines(TokenStream::new(source));
+    omitted_spaces(indented_tokens(lines.into_iter()).unwrap())
+        .into_iter()
+        .collect()
+}
+
+fn main() {
+    let shapes: [Shape; 3] = [
+        ("parens", parens),
+        ("applications", applications),
+        ("blocks", blocks),
+    ];
+    println!("{:>12} {:>6} {:>10}", "nesting", "depth", "parse");
+    for (name, synthetic) in shapes {
+        for depth in [10, 50, 100, 200] {
+            let tokens = tokens(&synthetic(depth));
+
+            let start = Instant::now();
+            let block = Parser::new(tokens).parse_module().unwrap();
+            let parsed = start.elapsed();
+
+            assert_eq!(block.stmts.len(), 1);
+            println!("{name:>12} {depth:>6} {parsed:>10.2?}");
+        }
+    }
+}
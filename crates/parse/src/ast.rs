@@ -1,57 +1,264 @@
-use lex::{Operator, SourceLocation, SourceObject, SourceSpan};
+use lex::{Operator, SourceLocation, SourceSpan};
 
-#[derive(Debug, Clone, SourceObject)]
+/// Identifies a node among all those built by the same parser, see [`crate::tables`] for
+/// keeping information about nodes outside of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(pub u32);
+
+traverse::leaves!(NodeId);
+
+#[derive(Debug, Clone, SourceObject, Serialize, Deserialize, Visitable)]
+#[visit(stmt)]
 pub struct Statement {
+    pub(crate) id: NodeId,
     pub(crate) loc: SourceLocation,
     pub(crate) span: SourceSpan,
 
     pub stmt: Stmt,
 }
 
-#[derive(Debug, Clone)]
+impl Statement {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+}
+
+/// Nodes compare by structure, wherever they come from.
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        self.stmt == other.stmt
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
 pub enum Stmt {
-    NameDeclaration { name: String, value: Expression },
+    NameDeclaration { name: String, ty: Option<TypeExpression>, value: Expression },
+    Destructure { pattern: Pattern, ty: Option<TypeExpression>, value: Expression },
     WhileStmt { pred: Expression, body: StatementBlock },
-    Expression(Expr),
+    IfStmt {
+        pred: Expression,
+        body: StatementBlock,
+        elifs: Vec<(Expression, StatementBlock)>,
+        else_body: Option<StatementBlock>,
+    },
+    ForStmt { name: String, iterable: Expression, body: StatementBlock },
+    Break,
+    Continue,
+    FunctionDef {
+        name: String,
+        params: Vec<(String, Option<TypeExpression>)>,
+        ret: Option<TypeExpression>,
+        body: StatementBlock,
+    },
+    StructDef { name: String, fields: Vec<(String, TypeExpression)> },
+    EnumDef { name: String, variants: Vec<(String, Vec<TypeExpression>)> },
+    MatchStmt { scrutinee: Expression, arms: Vec<(Pattern, StatementBlock)> },
+    /// `return`, or `return <value>`. Without one the function returns `()`.
+    Return { value: Option<Expression> },
+    /// `import foo.bar`
+    Import { path: Vec<String> },
+    /// `from foo.bar import baz, qux`
+    FromImport { path: Vec<String>, names: Vec<String> },
+    /// A top-level item marked with `pub`, visible to the modules importing it.
+    Public(Box<Statement>),
+    Expression(Expression),
 }
 
-#[derive(Debug, Clone, SourceObject)]
+/// The root of the tree, which brings the [`crate::visit`], [`crate::visit_mut`] and
+/// [`crate::fold`] visitors with a method for each of the nodes.
+#[derive(Debug, Clone, SourceObject, Serialize, Deserialize, Visitable)]
+#[visit(
+    block,
+    nodes(
+        stmt = "Statement",
+        expression = "Expression",
+        expr = "Expr",
+        type_expression = "TypeExpression",
+        type_expr = "TypeExpr",
+        pattern = "Pattern",
+        pat = "Pat",
+    )
+)]
 pub struct StatementBlock {
+    pub(crate) id: NodeId,
     pub(crate) loc: SourceLocation,
     pub(crate) span: SourceSpan,
 
     pub stmts: Vec<Statement>,
 }
 
+impl StatementBlock {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+}
 
-#[derive(Debug, Clone, SourceObject)]
+impl PartialEq for StatementBlock {
+    fn eq(&self, other: &Self) -> bool {
+        self.stmts == other.stmts
+    }
+}
+
+
+#[derive(Debug, Clone, SourceObject, Serialize, Deserialize, Visitable)]
+#[visit(expression)]
 pub struct Expression {
+    pub(crate) id: NodeId,
     pub(crate) loc: SourceLocation,
     pub(crate) span: SourceSpan,
 
     pub expr: Expr,
 }
 
-#[derive(Debug, Clone)]
+impl Expression {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.expr == other.expr
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+#[visit(expr)]
 pub enum Expr {
     IntegerLiteral(String),
     Binop(BinopExpr),
     Name(String),
     FunctionApplication(FunctionApplication),
     Grouping { expr: Box<Expression> },
+    Lambda(Lambda),
+    Range(RangeExpr),
+    Record(RecordExpr),
+    FieldAccess(FieldAccess),
+    Array(ArrayExpr),
+    Index(IndexExpr),
+    Tuple(TupleExpr),
 }
 
-use r#macro::SourceObject;
+use r#macro::{SourceObject, Visitable};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
 pub struct BinopExpr {
     pub op: Operator,
     pub lhs: Box<Expression>,
     pub rhs: Box<Expression>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+pub struct RangeExpr {
+    pub start: Box<Expression>,
+    pub end: Box<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
 pub struct FunctionApplication {
     pub func: Box<Expression>,
     pub args: Vec<Expression>,
 }
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+pub struct Lambda {
+    pub params: Vec<String>,
+    pub body: Box<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+pub struct RecordExpr {
+    pub name: String,
+    pub fields: Vec<(String, Expression)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+pub struct FieldAccess {
+    pub base: Box<Expression>,
+    pub field: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+pub struct ArrayExpr {
+    pub elements: Vec<Expression>,
+}
+
+/// `base[index]`, slicing is indexing with a range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+pub struct IndexExpr {
+    pub base: Box<Expression>,
+    pub index: Box<Expression>,
+}
+
+/// `(a, b)`, the unit value `()` is the empty tuple.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+pub struct TupleExpr {
+    pub elements: Vec<Expression>,
+}
+
+#[derive(Debug, Clone, SourceObject, Serialize, Deserialize, Visitable)]
+#[visit(type_expression)]
+pub struct TypeExpression {
+    pub(crate) id: NodeId,
+    pub(crate) loc: SourceLocation,
+    pub(crate) span: SourceSpan,
+
+    pub ty: TypeExpr,
+}
+
+impl TypeExpression {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+}
+
+impl PartialEq for TypeExpression {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+#[visit(type_expr)]
+pub enum TypeExpr {
+    Named(String),
+    /// `Range<Int>`
+    Generic { name: String, args: Vec<TypeExpression> },
+    /// `(Int, Int) -> Bool`
+    Function { params: Vec<TypeExpression>, ret: Box<TypeExpression> },
+    Tuple(Vec<TypeExpression>),
+    Array(Box<TypeExpression>),
+}
+
+#[derive(Debug, Clone, SourceObject, Serialize, Deserialize, Visitable)]
+#[visit(pattern)]
+pub struct Pattern {
+    pub(crate) id: NodeId,
+    pub(crate) loc: SourceLocation,
+    pub(crate) span: SourceSpan,
+
+    pub pat: Pat,
+}
+
+impl Pattern {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pat == other.pat
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Visitable)]
+#[visit(pat)]
+pub enum Pat {
+    Wildcard,
+    Name(String),
+    Integer(String),
+    Constructor { name: String, args: Vec<Pattern> },
+    Tuple(Vec<Pattern>),
+}
@@ -0,0 +1,72 @@
+//! Textual dumps of syntax trees for golden tests and external tools.
+//!
+//! Both formats share serde's data model: structs become maps of their fields, enum
+//! variants are tagged with their name, e.g. `{"Name": "x"}`, and spans are `[begin, end]`.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::StatementBlock;
+
+/// Pretty-printed JSON, with fields in declaration order.
+pub fn to_json(tree: &impl Serialize) -> String {
+    serde_json::to_string_pretty(tree).expect("syntax trees always serialize")
+}
+
+/// Reads back a tree however deeply it nests: the recursion limit of `serde_json` is lifted,
+/// and the stack grows onto the heap as needed instead.
+pub fn from_json<T: DeserializeOwned>(json: &str) -> serde_json::Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    deserializer.disable_recursion_limit();
+    let value = T::deserialize(serde_stacker::Deserializer::new(&mut deserializer))?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Reads back an AST written by [`to_json`].
+pub fn ast_from_json(json: &str) -> serde_json::Result<StatementBlock> {
+    from_json(json)
+}
+
+/// A single-line S-expression: maps are property lists, `(:field value ...)`, sequences
+/// are plain lists and a missing value is `nil`.
+pub fn to_sexp(tree: &impl Serialize) -> String {
+    let value = serde_json::to_value(tree).expect("syntax trees always serialize");
+    let mut out = String::new();
+    write_sexp(&value, &mut out);
+    out
+}
+
+fn write_sexp(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("nil"),
+        Value::Bool(true) => out.push_str("#t"),
+        Value::Bool(false) => out.push_str("#f"),
+        Value::Number(number) => out.push_str(&number.to_string()),
+        // JSON string escapes are valid in S-expression strings as well
+        Value::String(string) => out.push_str(&Value::String(string.to_owned()).to_string()),
+        Value::Array(elements) => {
+            out.push('(');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_sexp(element, out);
+            }
+            out.push(')');
+        }
+        Value::Object(fields) => {
+            out.push('(');
+            for (i, (field, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                out.push(':');
+                out.push_str(field);
+                out.push(' ');
+                write_sexp(value, out);
+            }
+            out.push(')');
+        }
+    }
+}
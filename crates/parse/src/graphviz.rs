@@ -1,125 +1,298 @@
 use std::io::{self, Write};
 
-use crate::ast::{Expression, Statement, StatementBlock, Expr, BinopExpr, Stmt, FunctionApplication};
+use parse::{visit::Visit, Expr, Pat, Statement, StatementBlock, Stmt, TypeExpr};
 
 #[derive(Clone, Default)]
 pub struct AstGraphvizVisualizer {
     pub nodes: Vec<(i32, String)>,
     pub edges: Vec<(i32, i32, String)>,
     pub counter: i32,
+    /// The node the next one drawn hangs from, along with the label of the edge to it.
+    parent: Option<(i32, String)>,
 }
 
 impl AstGraphvizVisualizer {
-    #[must_use]
+    /// Draws a node, below the one given to [`AstGraphvizVisualizer::below`] beforehand.
     pub fn new_node(&mut self, label: &str) -> i32 {
         self.counter += 1;
         self.nodes.push((self.counter, label.to_owned()));
+        if let Some((parent, edge)) = self.parent.take() {
+            self.new_edge(parent, self.counter, &edge);
+        }
         self.counter
     }
 
+    /// Draws a node without children below `parent`.
+    pub fn new_leaf(&mut self, parent: i32, edge: &str, label: &str) {
+        self.below(parent, edge).new_node(label);
+    }
+
     pub fn new_edge(&mut self, start: i32, end: i32, label: &str) {
         self.edges.push((start, end, label.to_owned()))
     }
 
+    /// Hangs the next node drawn from `parent`, along an edge labeled `edge`.
+    fn below(&mut self, parent: i32, edge: &str) -> &mut Self {
+        self.parent = Some((parent, edge.to_owned()));
+        self
+    }
+
     pub fn dump<W: Write>(&self, out: &mut W) -> io::Result<()> {
-        out.write("digraph {\n".as_bytes())?;
-        out.write("\trankdir=LR;\n".as_bytes())?;
+        out.write_all("digraph {\n".as_bytes())?;
+        out.write_all("\trankdir=LR;\n".as_bytes())?;
         for (vert, label) in &self.nodes {
-            out.write(format!("\t{vert} [label=\"{label}\"]\n").as_bytes())?;
+            out.write_all(format!("\t{vert} [label=\"{label}\"]\n").as_bytes())?;
         }
         for (start, end, label) in &self.edges {
-            out.write(format!("\t{start}->{end} [label=\"{label}\"]\n").as_bytes())?;
+            out.write_all(format!("\t{start}->{end} [label=\"{label}\"]\n").as_bytes())?;
         }
-        out.write("}\n".as_bytes())?;
+        out.write_all("}\n".as_bytes())?;
         Ok(())
     }
+}
 
-    
-    pub fn visit_stmt_block(&mut self, block: &StatementBlock) -> i32 {
+impl<'ast> Visit<'ast> for AstGraphvizVisualizer {
+    fn visit_block(&mut self, block: &'ast StatementBlock) {
         let this = self.new_node("Block");
         for (i, stmt) in block.stmts.iter().enumerate() {
-            let stmt = self.visit_stmt(stmt);
-            self.new_edge(this, stmt, (i + 1).to_string().as_str());
+            self.below(this, &(i + 1).to_string()).visit_stmt(stmt);
         }
-
-        this
     }
 
-    pub fn visit_stmt(&mut self, stmt: &Statement) -> i32 {
+    fn visit_stmt(&mut self, stmt: &'ast Statement) {
         match &stmt.stmt {
-            Stmt::NameDeclaration { name, value } => {
+            Stmt::NameDeclaration { name, ty, value } => {
                 let this = self.new_node("Name Declaration");
-                let name = self.new_node(name.as_str());
-                let value = self.visit_expression(&value);
-
-                self.new_edge(this, name, "name");
-                self.new_edge(this, value, "value");
-
-                this
-            },
+                self.new_leaf(this, "name", name);
+                self.below(this, "value").visit_expression(value);
+                if let Some(ty) = ty {
+                    self.below(this, "type").visit_type_expression(ty);
+                }
+            }
+            Stmt::Destructure { pattern, ty, value } => {
+                let this = self.new_node("Destructure");
+                self.below(this, "pattern").visit_pattern(pattern);
+                self.below(this, "value").visit_expression(value);
+                if let Some(ty) = ty {
+                    self.below(this, "type").visit_type_expression(ty);
+                }
+            }
             Stmt::WhileStmt { pred, body } => {
                 let this = self.new_node("While");
-                let pred = self.visit_expression(pred);
-                let body = self.visit_stmt_block(body);
+                self.below(this, "pred").visit_expression(pred);
+                self.below(this, "body").visit_block(body);
+            }
+            Stmt::IfStmt { pred, body, elifs, else_body } => {
+                let this = self.new_node("If");
+                self.below(this, "pred").visit_expression(pred);
+                self.below(this, "body").visit_block(body);
+
+                for (i, (pred, body)) in elifs.iter().enumerate() {
+                    let elif = self.below(this, &format!("elif {}", i + 1)).new_node("Elif");
+                    self.below(elif, "pred").visit_expression(pred);
+                    self.below(elif, "body").visit_block(body);
+                }
+
+                if let Some(else_body) = else_body {
+                    self.below(this, "else").visit_block(else_body);
+                }
+            }
+            Stmt::ForStmt { name, iterable, body } => {
+                let this = self.new_node("For");
+                self.new_leaf(this, "name", name);
+                self.below(this, "in").visit_expression(iterable);
+                self.below(this, "body").visit_block(body);
+            }
+            Stmt::Break => {
+                self.new_node("Break");
+            }
+            Stmt::Continue => {
+                self.new_node("Continue");
+            }
+            Stmt::FunctionDef { name, params, ret, body } => {
+                let this = self.new_node("Function Definition");
+                self.new_leaf(this, "name", name);
+
+                for (i, (param, ty)) in params.iter().enumerate() {
+                    let param = self.below(this, &format!("param {}", i + 1)).new_node(param);
+                    if let Some(ty) = ty {
+                        self.below(param, "type").visit_type_expression(ty);
+                    }
+                }
+
+                if let Some(ret) = ret {
+                    self.below(this, "ret").visit_type_expression(ret);
+                }
+                self.below(this, "body").visit_block(body);
+            }
+            Stmt::StructDef { name, fields } => {
+                let this = self.new_node("Struct Definition");
+                self.new_leaf(this, "name", name);
+                for (field, ty) in fields {
+                    self.below(this, field).visit_type_expression(ty);
+                }
+            }
+            Stmt::EnumDef { name, variants } => {
+                let this = self.new_node("Enum Definition");
+                self.new_leaf(this, "name", name);
 
-                self.new_edge(this, pred, "pred");
-                self.new_edge(this, body, "body");
+                for (variant, fields) in variants {
+                    let variant = self.below(this, "variant").new_node(variant);
+                    for (i, ty) in fields.iter().enumerate() {
+                        self.below(variant, &(i + 1).to_string()).visit_type_expression(ty);
+                    }
+                }
+            }
+            Stmt::MatchStmt { scrutinee, arms } => {
+                let this = self.new_node("Match");
+                self.below(this, "scrutinee").visit_expression(scrutinee);
 
-                this
-            },
-            Stmt::Expression(expr) => self.visit_expr(expr),
+                for (pattern, body) in arms {
+                    let arm = self.below(this, "").new_node("Arm");
+                    self.below(arm, "pattern").visit_pattern(pattern);
+                    self.below(arm, "body").visit_block(body);
+                }
+            }
+            Stmt::Return { value } => {
+                let this = self.new_node("Return");
+                if let Some(value) = value {
+                    self.below(this, "value").visit_expression(value);
+                }
+            }
+            Stmt::Import { path } => {
+                let this = self.new_node("Import");
+                self.new_leaf(this, "module", &path.join("."));
+            }
+            Stmt::FromImport { path, names } => {
+                let this = self.new_node("From Import");
+                self.new_leaf(this, "module", &path.join("."));
+                for name in names {
+                    self.new_leaf(this, "name", name);
+                }
+            }
+            Stmt::Public(item) => {
+                let this = self.new_node("Pub");
+                self.below(this, "item").visit_stmt(item);
+            }
+            // drawn in place of the statement, hanging from the same parent
+            Stmt::Expression(expr) => self.visit_expression(expr),
         }
     }
 
-    pub fn visit_expr(&mut self, expr: &Expr) -> i32 {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
         match expr {
-            Expr::Name(name) => {
-                self.new_node(name.as_str())
-            },
+            Expr::Name(name) | Expr::IntegerLiteral(name) => {
+                self.new_node(name);
+            }
             Expr::Binop(binop) => {
-                self.visit_binop(binop)
-            },
-            Expr::IntegerLiteral(number) => {
-                self.new_node(number.as_str())
-            },
+                let this = self.new_node(binop.op.into());
+                self.below(this, "lhs").visit_expression(&binop.lhs);
+                self.below(this, "rhs").visit_expression(&binop.rhs);
+            }
             Expr::FunctionApplication(fa) => {
-                self.visit_function_application(fa)
-            },
+                let this = self.new_node("Function Application");
+                self.below(this, "func").visit_expression(&fa.func);
+                for (i, arg) in fa.args.iter().enumerate() {
+                    self.below(this, &(i + 1).to_string()).visit_expression(arg);
+                }
+            }
             Expr::Grouping { expr } => {
                 let this = self.new_node("Grouping");
-                let grouped = self.visit_expression(expr);
-                self.new_edge(this, grouped, "");               
-                this
+                self.below(this, "").visit_expression(expr);
+            }
+            Expr::Lambda(lambda) => {
+                let this = self.new_node("Lambda");
+                for (i, param) in lambda.params.iter().enumerate() {
+                    self.new_leaf(this, &format!("param {}", i + 1), param);
+                }
+                self.below(this, "body").visit_expression(&lambda.body);
+            }
+            Expr::Range(range) => {
+                let this = self.new_node("Range");
+                self.below(this, "start").visit_expression(&range.start);
+                self.below(this, "end").visit_expression(&range.end);
+            }
+            Expr::Record(record) => {
+                let this = self.new_node(&format!("Record {}", record.name));
+                for (field, value) in &record.fields {
+                    self.below(this, field).visit_expression(value);
+                }
+            }
+            Expr::FieldAccess(access) => {
+                let this = self.new_node(&format!(".{}", access.field));
+                self.below(this, "base").visit_expression(&access.base);
+            }
+            Expr::Array(array) => {
+                let this = self.new_node("Array");
+                for (i, element) in array.elements.iter().enumerate() {
+                    self.below(this, &i.to_string()).visit_expression(element);
+                }
+            }
+            Expr::Index(index) => {
+                let this = self.new_node("Index");
+                self.below(this, "base").visit_expression(&index.base);
+                self.below(this, "index").visit_expression(&index.index);
+            }
+            Expr::Tuple(tuple) => {
+                let this = self.new_node("Tuple");
+                for (i, element) in tuple.elements.iter().enumerate() {
+                    self.below(this, &i.to_string()).visit_expression(element);
+                }
             }
         }
     }
 
-    pub fn visit_expression(&mut self, expr: &Expression) -> i32 {
-        self.visit_expr(&expr.expr)
-    }
-
-    pub fn visit_function_application(&mut self, fa: &FunctionApplication) -> i32 {
-        let this = self.new_node("Function Application");
-
-        let func = self.visit_expression(&fa.func);
-        self.new_edge(this, func, "func");
-
-        for (i, arg) in fa.args.iter().enumerate() {
-            let arg = self.visit_expression(arg);
-            self.new_edge(this, arg, (i + 1).to_string().as_str());
+    fn visit_type_expr(&mut self, ty: &'ast TypeExpr) {
+        match ty {
+            TypeExpr::Named(name) => {
+                self.new_node(name);
+            }
+            TypeExpr::Generic { name, args } => {
+                let this = self.new_node(name);
+                for (i, arg) in args.iter().enumerate() {
+                    self.below(this, &(i + 1).to_string()).visit_type_expression(arg);
+                }
+            }
+            TypeExpr::Function { params, ret } => {
+                let this = self.new_node("Function Type");
+                for (i, param) in params.iter().enumerate() {
+                    self.below(this, &format!("param {}", i + 1)).visit_type_expression(param);
+                }
+                self.below(this, "ret").visit_type_expression(ret);
+            }
+            TypeExpr::Tuple(elements) => {
+                let this = self.new_node("Tuple Type");
+                for (i, element) in elements.iter().enumerate() {
+                    self.below(this, &i.to_string()).visit_type_expression(element);
+                }
+            }
+            TypeExpr::Array(elem) => {
+                let this = self.new_node("Array Type");
+                self.below(this, "elem").visit_type_expression(elem);
+            }
         }
-
-        this
     }
 
-    pub fn visit_binop(&mut self, binop: &BinopExpr) -> i32 {
-        let this = self.new_node(binop.op.into());
-        let lhs = self.visit_expression(&binop.lhs);
-        let rhs = self.visit_expression(&binop.rhs);
-
-        self.new_edge(this, lhs, "lhs");
-        self.new_edge(this, rhs, "rhs");
-
-        this
+    fn visit_pat(&mut self, pat: &'ast Pat) {
+        match pat {
+            Pat::Wildcard => {
+                self.new_node("_");
+            }
+            Pat::Name(name) | Pat::Integer(name) => {
+                self.new_node(name);
+            }
+            Pat::Constructor { name, args } => {
+                let this = self.new_node(name);
+                for (i, arg) in args.iter().enumerate() {
+                    self.below(this, &(i + 1).to_string()).visit_pattern(arg);
+                }
+            }
+            Pat::Tuple(elements) => {
+                let this = self.new_node("Tuple");
+                for (i, element) in elements.iter().enumerate() {
+                    self.below(this, &i.to_string()).visit_pattern(element);
+                }
+            }
+        }
     }
 }
@@ -1,5 +1,10 @@
 mod ast;
 mod parser;
+mod printer;
+pub mod dump;
+pub mod syntax;
+pub mod tables;
 
 pub use parser::*;
 pub use ast::*;
+pub use printer::*;
@@ -1,16 +1,15 @@
-mod ast;
-mod parser;
 mod graphviz;
 
 use graphviz::AstGraphvizVisualizer;
-use parser::*;
+use parse::visit::Visit;
+use parse::*;
 use lex::*;
 use std::fs;
 
 fn main() {
     let contents = fs::read_to_string("sample.wb").expect("Should have been able to read the file");
     let tokens = try_tokenize(contents.as_str());
-    let indented_toks = indented_tokens(tokens);
+    let indented_toks = indented_tokens(logical_lines(tokens).into_iter()).unwrap();
 
     let toks: Vec<Token> = omitted_spaces(indented_toks).into_iter().collect();
     for (i, tok) in toks.iter().enumerate() {
@@ -18,11 +17,17 @@ fn main() {
     }
     
     let mut parser = Parser::new(toks);
-    let block = parser.parse_stmt_block().unwrap();
+    let block = parser.parse_module().unwrap();
 
     let mut visitor = AstGraphvizVisualizer::default();
-    visitor.visit_stmt_block(&block);
+    visitor.visit_block(&block);
     visitor.dump(&mut fs::File::create("out.dot").unwrap()).unwrap();
 
-    println!("{block:?}")
+    println!("{block:?}");
+
+    let json = parse::dump::to_json(&block);
+    fs::write("out.json", &json).unwrap();
+    println!("{}", parse::dump::to_sexp(&block));
+    let file = parse::syntax::parse_lossless(contents.as_str()).unwrap();
+    print!("{}", file.syntax().debug_dump());
 }
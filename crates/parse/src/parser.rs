@@ -1,12 +1,53 @@
-use lex::{Keyword, Operator, Punctuation, SourceLocation, SourceObject, Token, TokenKind};
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+use lex::{
+    IndentationError, Keyword, Operator, Punctuation, SourceLocation, SourceObject, Token,
+    TokenKind,
+};
 
 use crate::ast::{
-    BinopExpr, Expr, Expression, FunctionApplication, Statement, StatementBlock, Stmt,
+    ArrayExpr, BinopExpr, Expr, Expression, FieldAccess, FunctionApplication, IndexExpr, Lambda,
+    NodeId, Pat, Pattern, RangeExpr, RecordExpr, Statement, StatementBlock, Stmt, TupleExpr,
+    TypeExpr, TypeExpression,
 };
+use crate::syntax::{Emitted, GreenNode, SyntaxBuilder, SyntaxCheckpoint, SyntaxKind};
 
 pub struct Parser {
     cursor: usize,
     tokens: Vec<Token>,
+    /// Outcomes of the expression rules by where they started, along with where they stopped,
+    /// so that alternatives sharing a prefix do not parse it again after backtracking.
+    memo: HashMap<(Rule, usize), Memo>,
+    next_id: u32,
+    /// The fault furthest into the input of those backtracked from, which is where the input
+    /// stops making sense when no alternative gets any further.
+    furthest: Option<ParserFault>,
+    /// The lossless tree of the source, built along with the AST when there is a source to
+    /// take the tokens the parser does not see from.
+    syntax: Option<SyntaxBuilder>,
+}
+
+/// What a memoized rule gave when tried at some token, see [`Parser::memoized`].
+struct Memo {
+    result: ParserResult<Expression>,
+    end: usize,
+    emitted: Option<Emitted>,
+}
+
+/// Where the parser stood, to rewind to when an alternative fails.
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    cursor: usize,
+    syntax: Option<SyntaxCheckpoint>,
+}
+
+/// The memoized rules, see [`Parser::memoized`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Rule {
+    Expression,
+    Binop,
+    Application,
+    Primary,
 }
 
 #[derive(Clone, Debug)]
@@ -16,6 +57,44 @@ pub struct ParserFault {
 
 pub type ParserResult<T> = Result<T, ParserFault>;
 
+/// Why source text did not parse, either its lines are indented wrongly
+/// or its tokens do not make up a module.
+#[derive(Clone, Debug)]
+pub enum SyntaxError {
+    Indentation(IndentationError),
+    Parser(ParserFault),
+}
+
+impl SyntaxError {
+    pub fn loc(&self) -> SourceLocation {
+        match self {
+            SyntaxError::Indentation(err) => err.loc(),
+            SyntaxError::Parser(fault) => fault.loc,
+        }
+    }
+}
+
+impl From<IndentationError> for SyntaxError {
+    fn from(err: IndentationError) -> Self {
+        SyntaxError::Indentation(err)
+    }
+}
+
+impl From<ParserFault> for SyntaxError {
+    fn from(fault: ParserFault) -> Self {
+        SyntaxError::Parser(fault)
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyntaxError::Indentation(err) => write!(f, "{err}"),
+            SyntaxError::Parser(_) => write!(f, "syntax error"),
+        }
+    }
+}
+
 pub fn precedence_of(op: &Operator) -> u8 {
     use Operator::*;
 
@@ -23,15 +102,110 @@ pub fn precedence_of(op: &Operator) -> u8 {
         Mul | Div | Mod => 5,
         Add | Sub => 6,
         Greater | Less => 9,
-        Equals => 10,
+        And => 11,
+        Or => 12,
+        Equals => 13,
     }
 }
 
+/// Whether `op` compares its operands. Comparisons chain: `a < b < c`, parsed as a comparison
+/// whose left operand is another one without parentheses around it, means `a < b and b < c`.
+pub fn is_comparison(op: &Operator) -> bool {
+    matches!(op, Operator::Greater | Operator::Less)
+}
+
+/// Whether `fault` lies further into the input than `than`, if there is one.
+fn is_further(fault: &ParserFault, than: Option<&ParserFault>) -> bool {
+    than.is_none_or(|than| than.loc.index < fault.loc.index)
+}
+
 impl Parser {
     pub fn new(tokens: impl IntoIterator<Item = Token>) -> Self {
         Self {
             cursor: 0,
             tokens: tokens.into_iter().collect(),
+            memo: HashMap::new(),
+            next_id: 0,
+            furthest: None,
+            syntax: None,
+        }
+    }
+
+    /// A parser that also builds the lossless tree of `source`, which `tokens` were lexed from.
+    pub(crate) fn with_syntax(tokens: impl IntoIterator<Item = Token>, source: &str) -> Self {
+        Self {
+            syntax: Some(SyntaxBuilder::new(source)),
+            ..Self::new(tokens)
+        }
+    }
+
+    /// The root of the lossless tree, once the whole source is parsed.
+    pub(crate) fn finish_syntax(&mut self) -> Option<Rc<GreenNode>> {
+        self.syntax.take().map(SyntaxBuilder::finish)
+    }
+
+    /// Hands out the id of a new node, unique within everything this parser builds.
+    fn node_id(&mut self) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Runs `parser` unless `rule` was already tried at the cursor, in which case its
+    /// outcome is replayed, along with the syntax it built. This keeps backtracking over nested
+    /// expressions linear.
+    fn memoized(
+        &mut self,
+        rule: Rule,
+        parser: fn(&mut Self) -> ParserResult<Expression>,
+    ) -> ParserResult<Expression> {
+        let start = self.cursor;
+        self.start_node();
+        if let Some(memo) = self.memo.get(&(rule, start)) {
+            self.cursor = memo.end;
+            if let (Some(syntax), Some(emitted)) = (&mut self.syntax, &memo.emitted) {
+                syntax.replay(emitted);
+            }
+            return memo.result.to_owned();
+        }
+
+        let checkpoint = self.checkpoint();
+        let result = parser(self);
+        let emitted = self
+            .syntax
+            .as_ref()
+            .zip(checkpoint.syntax)
+            .map(|(syntax, checkpoint)| syntax.emitted_since(checkpoint));
+        self.memo.insert(
+            (rule, start),
+            Memo {
+                result: result.to_owned(),
+                end: self.cursor,
+                emitted,
+            },
+        );
+        result
+    }
+
+    /// Starts a node of the syntax tree at the current token, returning where it starts.
+    fn start_node(&mut self) -> usize {
+        let offset = self.current().source_span().0;
+        self.syntax
+            .as_mut()
+            .map_or(0, |syntax| syntax.start_node(offset))
+    }
+
+    /// Makes what was parsed since `start` a node of the syntax tree.
+    fn finish_node(&mut self, start: usize, kind: SyntaxKind) {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.finish_node(start, kind);
+        }
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            cursor: self.cursor,
+            syntax: self.syntax.as_ref().map(SyntaxBuilder::checkpoint),
         }
     }
 
@@ -39,31 +213,47 @@ impl Parser {
         &self.tokens[self.cursor]
     }
 
+    fn previous(&self) -> &Token {
+        &self.tokens[self.cursor - 1]
+    }
+
+    /// Whether no whitespace separates the current token from the previous one.
+    fn is_adjacent(&self) -> bool {
+        self.cursor > 0 && self.previous().source_span().1 == self.current().source_span().0
+    }
+
+    /// Moves past the current token, adding it to the syntax tree.
+    fn bump(&mut self) {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.token(&self.tokens[self.cursor]);
+        }
+        self.cursor += 1;
+    }
+
     fn eat_if(&mut self, pred: impl FnOnce(&Token) -> bool) -> ParserResult<&Token> {
-        let cur = &self.tokens[self.cursor];
-        if pred(cur) {
-            self.cursor += 1;
-            Ok(cur)
+        if pred(self.current()) {
+            self.bump();
+            Ok(self.previous())
         } else {
             Err(ParserFault {
-                loc: cur.source_location(),
+                loc: self.current().source_location(),
             })
         }
     }
 
     fn eat<T>(&mut self, map: impl FnOnce(&Token) -> ParserResult<T>) -> ParserResult<T> {
-        let ret = map(&self.tokens[self.cursor]);
+        let ret = map(self.current());
         if ret.is_ok() {
-            self.cursor += 1;
+            self.bump();
         }
         ret
     }
 
     fn eat_variant(&mut self, kind_variant: TokenKind) -> ParserResult<&Token> {
-        let cur = &self.tokens[self.cursor];
+        let cur = self.current();
         if std::mem::discriminant(&kind_variant) == std::mem::discriminant(&cur.kind) {
-            self.cursor += 1;
-            Ok(cur)
+            self.bump();
+            Ok(self.previous())
         } else {
             Err(ParserFault {
                 loc: cur.source_location(),
@@ -71,33 +261,83 @@ impl Parser {
         }
     }
 
+    fn eat_keyword(&mut self, keyword: Keyword) -> ParserResult<&Token> {
+        self.eat_if(|token| matches!(&token.kind, TokenKind::Keyword(kw) if *kw == keyword))
+    }
+
+    fn eat_punctuation(&mut self, punctuation: Punctuation) -> ParserResult<&Token> {
+        self.eat_if(|token| matches!(&token.kind, TokenKind::Punctuation(p) if *p == punctuation))
+    }
+
+    /// Rewinds to `checkpoint` after an alternative failed with `fault`, remembering the fault
+    /// when it got further than any before it.
+    fn backtrack(&mut self, checkpoint: Checkpoint, fault: ParserFault) {
+        self.cursor = checkpoint.cursor;
+        if let (Some(syntax), Some(checkpoint)) = (&mut self.syntax, checkpoint.syntax) {
+            syntax.rewind(checkpoint);
+        }
+        if is_further(&fault, self.furthest.as_ref()) {
+            self.furthest = Some(fault);
+        }
+    }
+
+    /// The fault to report for `fault`, or for one further into the input that some
+    /// alternative backtracked from.
+    fn furthest_fault(&self, fault: ParserFault) -> ParserFault {
+        match &self.furthest {
+            Some(furthest) if is_further(furthest, Some(&fault)) => furthest.to_owned(),
+            _ => fault,
+        }
+    }
+
+    /// Tries each of `parsers` in turn. When all of them fail, the fault is the one of the
+    /// alternative that got the furthest.
     fn one_of<T>(&mut self, parsers: &[fn(&mut Self) -> ParserResult<T>]) -> ParserResult<T> {
-        let cursor = self.cursor;
+        let checkpoint = self.checkpoint();
+        let mut furthest: Option<ParserFault> = None;
         for parser in parsers {
             match parser(self) {
                 Ok(good) => return Ok(good),
-                Err(_err) => {
-                    self.cursor = cursor;
-                    continue;
+                Err(err) => {
+                    if is_further(&err, furthest.as_ref()) {
+                        furthest = Some(err.to_owned());
+                    }
+                    self.backtrack(checkpoint, err);
                 }
             }
         }
 
-        Err(ParserFault {
+        Err(furthest.unwrap_or_else(|| ParserFault {
             loc: self.current().source_location(),
-        })
+        }))
     }
 
     fn none_or_more<T>(&mut self, parser: fn(&mut Self) -> ParserResult<T>) -> Vec<T> {
         let mut results = vec![];
-        let mut cursor = self.cursor;
-        while let Ok(parsed) = parser(self) {
-            results.push(parsed);
-            cursor = self.cursor;
+        let mut checkpoint = self.checkpoint();
+        loop {
+            match parser(self) {
+                Ok(parsed) => {
+                    results.push(parsed);
+                    checkpoint = self.checkpoint();
+                }
+                Err(err) => {
+                    self.backtrack(checkpoint, err);
+                    return results;
+                }
+            }
         }
-        self.cursor = cursor;
+    }
 
-        results
+    fn optional<T>(&mut self, parser: fn(&mut Self) -> ParserResult<T>) -> Option<T> {
+        let checkpoint = self.checkpoint();
+        match parser(self) {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                self.backtrack(checkpoint, err);
+                None
+            }
+        }
     }
 
     fn one_or_more<T>(&mut self, parser: fn(&mut Self) -> ParserResult<T>) -> ParserResult<Vec<T>> {
@@ -114,167 +354,1007 @@ impl Parser {
     pub fn parse_stmt(&mut self) -> ParserResult<Statement> {
         self.one_of(&[
             |parser| parser.parse_while(),
+            |parser| parser.parse_if(),
+            |parser| parser.parse_for(),
+            |parser| parser.parse_function_def(),
+            |parser| parser.parse_struct_def(),
+            |parser| parser.parse_enum_def(),
+            |parser| parser.parse_match(),
+            |parser| parser.parse_pub(),
             |parser| {
-                let ret = parser.parse_name_decl()?;
-                parser.eat_variant(TokenKind::Newline)?;
+                let ret = parser.parse_simple_stmt()?;
+                parser.eat_terminator()?;
                 Ok(ret)
             },
+        ])
+    }
+
+    /// Parses a statement that fits on a line, which a `;` may follow with another one.
+    pub fn parse_simple_stmt(&mut self) -> ParserResult<Statement> {
+        self.one_of(&[
+            |parser| parser.parse_loop_control(),
+            |parser| parser.parse_import(),
+            |parser| parser.parse_return(),
+            |parser| parser.parse_name_decl(),
             |parser| {
-                let ret = parser.parse_expression()?;
-                parser.eat_variant(TokenKind::Newline)?;
+                let node = parser.start_node();
+                let expr = parser.parse_expression()?;
+                parser.finish_node(node, SyntaxKind::ExpressionStmt);
                 Ok(Statement {
-                    loc: ret.source_location(),
-                    span: ret.source_span(),
-                    stmt: Stmt::Expression(ret.expr),
+                    id: parser.node_id(),
+                    loc: expr.source_location(),
+                    span: expr.source_span(),
+                    stmt: Stmt::Expression(expr),
                 })
             },
         ])
     }
 
+    /// Eats what ends a simple statement, a newline or a `;` optionally followed by one,
+    /// returning whether the line is over.
+    fn eat_terminator(&mut self) -> ParserResult<bool> {
+        if self.eat_variant(TokenKind::Newline).is_ok() {
+            return Ok(true);
+        }
+        self.eat_punctuation(Punctuation::Semicolon)?;
+        Ok(self.eat_variant(TokenKind::Newline).is_ok())
+    }
+
+    /// Parses `import foo.bar` and `from foo.bar import baz, qux`.
+    pub fn parse_import(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let first = self.current();
+        let span_begin = first.source_span().0;
+        let loc = first.source_location();
+
+        let stmt = self.one_of(&[
+            |parser| {
+                parser.eat_keyword(Keyword::Import)?;
+                let path = parser.parse_module_path()?;
+                Ok(Stmt::Import { path })
+            },
+            |parser| {
+                parser.eat_keyword(Keyword::From)?;
+                let path = parser.parse_module_path()?;
+                parser.eat_keyword(Keyword::Import)?;
+
+                let mut names = vec![parser.parse_ident()?];
+                names.extend(parser.none_or_more(|parser| {
+                    parser.eat_punctuation(Punctuation::Comma)?;
+                    parser.parse_ident()
+                }));
+
+                Ok(Stmt::FromImport { path, names })
+            },
+        ])?;
+        let span_end = self.previous().source_span().1;
+        let kind = match stmt {
+            Stmt::Import { .. } => SyntaxKind::Import,
+            _ => SyntaxKind::FromImport,
+        };
+        self.finish_node(node, kind);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt,
+        })
+    }
+
+    /// Parses a dot-separated module path such as `foo.bar`.
+    pub fn parse_module_path(&mut self) -> ParserResult<Vec<String>> {
+        let mut path = vec![self.parse_ident()?];
+        path.extend(self.none_or_more(|parser| {
+            parser.eat_variant(TokenKind::Dot)?;
+            parser.parse_ident()
+        }));
+        Ok(path)
+    }
+
+    /// Parses `pub` followed by a function, struct, enum or name declaration.
+    pub fn parse_pub(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let pub_keyword = self.eat_keyword(Keyword::Pub)?;
+        let span_begin = pub_keyword.source_span().0;
+        let loc = pub_keyword.source_location();
+
+        let item = self.one_of(&[
+            |parser| parser.parse_function_def(),
+            |parser| parser.parse_struct_def(),
+            |parser| parser.parse_enum_def(),
+            |parser| {
+                let ret = parser.parse_name_decl()?;
+                parser.eat_terminator()?;
+                Ok(ret)
+            },
+        ])?;
+        let span_end = item.source_span().1;
+        self.finish_node(node, SyntaxKind::Public);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::Public(Box::new(item)),
+        })
+    }
+
     pub fn parse_while(&mut self) -> ParserResult<Statement> {
-        let while_keyword = self.eat_variant(TokenKind::Keyword(Keyword::While))?;
+        let node = self.start_node();
+        let while_keyword = self.eat_keyword(Keyword::While)?;
         let span_begin = while_keyword.source_span().0;
         let loc = while_keyword.source_location();
 
         let pred = self.parse_expression()?;
+        let body = self.parse_indented_block()?;
+        let span_end = body.span.1;
+        self.finish_node(node, SyntaxKind::While);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::WhileStmt { pred, body },
+        })
+    }
+
+    pub fn parse_for(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let for_keyword = self.eat_keyword(Keyword::For)?;
+        let span_begin = for_keyword.source_span().0;
+        let loc = for_keyword.source_location();
+
+        let name = self.parse_ident()?;
+        self.eat_keyword(Keyword::In)?;
+        let iterable = self.parse_expression()?;
+
+        let body = self.parse_indented_block()?;
+        let span_end = body.span.1;
+        self.finish_node(node, SyntaxKind::For);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::ForStmt {
+                name,
+                iterable,
+                body,
+            },
+        })
+    }
+
+    /// Parses either `break` or `continue`.
+    pub fn parse_loop_control(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let keyword = self.eat_if(|token| {
+            matches!(
+                token.kind,
+                TokenKind::Keyword(Keyword::Break | Keyword::Continue)
+            )
+        })?;
+        let (loc, span) = (keyword.source_location(), keyword.source_span());
+        let (stmt, kind) = match keyword.kind {
+            TokenKind::Keyword(Keyword::Break) => (Stmt::Break, SyntaxKind::Break),
+            _ => (Stmt::Continue, SyntaxKind::Continue),
+        };
+        self.finish_node(node, kind);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span,
+            stmt,
+        })
+    }
+
+    pub fn parse_if(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let if_keyword = self.eat_keyword(Keyword::If)?;
+        let span_begin = if_keyword.source_span().0;
+        let loc = if_keyword.source_location();
+
+        let pred = self.parse_expression()?;
+        let body = self.parse_indented_block()?;
+
+        let elifs = self.none_or_more(|parser| {
+            parser.eat_keyword(Keyword::Elif)?;
+            let pred = parser.parse_expression()?;
+            let body = parser.parse_indented_block()?;
+            Ok((pred, body))
+        });
+
+        let else_body = self.optional(|parser| {
+            parser.eat_keyword(Keyword::Else)?;
+            parser.parse_indented_block()
+        });
+
+        let span_end = match (&else_body, elifs.last()) {
+            (Some(else_body), _) => else_body.span.1,
+            (None, Some((_, elif_body))) => elif_body.span.1,
+            (None, None) => body.span.1,
+        };
+        self.finish_node(node, SyntaxKind::If);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::IfStmt {
+                pred,
+                body,
+                elifs,
+                else_body,
+            },
+        })
+    }
+
+    pub fn parse_function_def(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let fn_keyword = self.eat_keyword(Keyword::Fn)?;
+        let span_begin = fn_keyword.source_span().0;
+        let loc = fn_keyword.source_location();
+
+        let name = self.parse_ident()?;
+        let params = self.parse_params()?;
+        let ret = self.optional(|parser| {
+            parser.eat_variant(TokenKind::Arrow)?;
+            parser.parse_type_expr()
+        });
+        let body = self.parse_indented_block()?;
+        let span_end = body.span.1;
+        self.finish_node(node, SyntaxKind::FunctionDef);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::FunctionDef {
+                name,
+                params,
+                ret,
+                body,
+            },
+        })
+    }
+
+    /// Parses a parenthesized, comma-separated and possibly empty list of parameters.
+    pub fn parse_params(&mut self) -> ParserResult<Vec<(String, Option<TypeExpression>)>> {
+        self.eat_variant(TokenKind::LeftParenthese)?;
+
+        let mut params = vec![];
+        if let Some(first) = self.optional(Parser::parse_param) {
+            params.push(first);
+            params.extend(self.none_or_more(|parser| {
+                parser.eat_punctuation(Punctuation::Comma)?;
+                parser.parse_param()
+            }));
+        }
+
+        self.eat_variant(TokenKind::RightParenthese)?;
+
+        Ok(params)
+    }
+
+    /// Parses a parameter name, optionally followed by `: Type`.
+    pub fn parse_param(&mut self) -> ParserResult<(String, Option<TypeExpression>)> {
+        let name = self.parse_ident()?;
+        let ty = self.optional(Parser::parse_type_annotation);
+        Ok((name, ty))
+    }
+
+    /// Parses `: Type`.
+    pub fn parse_type_annotation(&mut self) -> ParserResult<TypeExpression> {
+        self.eat_punctuation(Punctuation::Colon)?;
+        self.parse_type_expr()
+    }
+
+    /// Parses a struct declaration, its fields either follow the colon on the same line
+    /// separated by commas or are listed one per line in an indented block.
+    pub fn parse_struct_def(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let struct_keyword = self.eat_keyword(Keyword::Struct)?;
+        let span_begin = struct_keyword.source_span().0;
+        let loc = struct_keyword.source_location();
+
+        let name = self.parse_ident()?;
+        self.eat_punctuation(Punctuation::Colon)?;
+
+        let fields = self.one_of(&[
+            |parser| {
+                let mut fields = vec![parser.parse_field_decl()?];
+                fields.extend(parser.none_or_more(|parser| {
+                    parser.eat_punctuation(Punctuation::Comma)?;
+                    parser.parse_field_decl()
+                }));
+                parser.eat_variant(TokenKind::Newline)?;
+                Ok(fields)
+            },
+            |parser| {
+                parser.eat_variant(TokenKind::Newline)?;
+                parser.eat_variant(TokenKind::Indent)?;
+                let fields = parser.one_or_more(|parser| {
+                    let field = parser.parse_field_decl()?;
+                    parser.eat_variant(TokenKind::Newline)?;
+                    Ok(field)
+                })?;
+                parser.eat_variant(TokenKind::Dendent)?;
+                Ok(fields)
+            },
+        ])?;
+        let span_end = self.previous().source_span().1;
+        self.finish_node(node, SyntaxKind::StructDef);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::StructDef { name, fields },
+        })
+    }
+
+    /// Parses `enum Name: Variant(Type, ...) | Variant | ...`.
+    pub fn parse_enum_def(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let enum_keyword = self.eat_keyword(Keyword::Enum)?;
+        let span_begin = enum_keyword.source_span().0;
+        let loc = enum_keyword.source_location();
+
+        let name = self.parse_ident()?;
+        self.eat_punctuation(Punctuation::Colon)?;
+
+        let mut variants = vec![self.parse_variant_decl()?];
+        variants.extend(self.none_or_more(|parser| {
+            parser.eat_variant(TokenKind::Pipe)?;
+            parser.parse_variant_decl()
+        }));
+        let span_end = self.previous().source_span().1;
 
-        self.eat_variant(TokenKind::Punctuation(Punctuation::Colon))?;
+        self.eat_variant(TokenKind::Newline)?;
+        self.finish_node(node, SyntaxKind::EnumDef);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::EnumDef { name, variants },
+        })
+    }
+
+    /// Parses `Variant` or `Variant(Type, ...)`.
+    pub fn parse_variant_decl(&mut self) -> ParserResult<(String, Vec<TypeExpression>)> {
+        let name = self.parse_ident()?;
+        let fields = self
+            .optional(|parser| {
+                parser.eat_variant(TokenKind::LeftParenthese)?;
+                let mut fields = vec![parser.parse_type_expr()?];
+                fields.extend(parser.none_or_more(|parser| {
+                    parser.eat_punctuation(Punctuation::Comma)?;
+                    parser.parse_type_expr()
+                }));
+                parser.eat_variant(TokenKind::RightParenthese)?;
+                Ok(fields)
+            })
+            .unwrap_or_default();
+
+        Ok((name, fields))
+    }
+
+    pub fn parse_match(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let match_keyword = self.eat_keyword(Keyword::Match)?;
+        let span_begin = match_keyword.source_span().0;
+        let loc = match_keyword.source_location();
+
+        let scrutinee = self.parse_expression()?;
+        self.eat_punctuation(Punctuation::Colon)?;
         self.eat_variant(TokenKind::Newline)?;
         self.eat_variant(TokenKind::Indent)?;
 
-        let body = self.parse_stmt_block()?;
-        let span_end = body.span.1;
+        let arms = self.one_or_more(|parser| {
+            let pattern = parser.parse_pattern()?;
+            let body = parser.parse_indented_block()?;
+            Ok((pattern, body))
+        })?;
+        let span_end = self.previous().source_span().1;
 
         self.eat_variant(TokenKind::Dendent)?;
+        self.finish_node(node, SyntaxKind::Match);
 
         Ok(Statement {
+            id: self.node_id(),
             loc,
             span: (span_begin, span_end),
-            stmt: Stmt::WhileStmt { pred, body },
+            stmt: Stmt::MatchStmt { scrutinee, arms },
+        })
+    }
+
+    pub fn parse_pattern(&mut self) -> ParserResult<Pattern> {
+        let node = self.start_node();
+        let span_begin = self.current().source_span().0;
+        let loc = self.current().source_location();
+        let parenthesized = matches!(self.current().kind, TokenKind::LeftParenthese);
+
+        let pat = self.one_of(&[
+            |parser| {
+                parser.eat_variant(TokenKind::Integer("".to_string()))?;
+                match &parser.previous().kind {
+                    TokenKind::Integer(int) => Ok(Pat::Integer(int.to_owned())),
+                    _ => unreachable!(),
+                }
+            },
+            |parser| {
+                let name = parser.parse_ident()?;
+                parser.eat_variant(TokenKind::LeftParenthese)?;
+                let mut args = vec![parser.parse_pattern()?];
+                args.extend(parser.none_or_more(|parser| {
+                    parser.eat_punctuation(Punctuation::Comma)?;
+                    parser.parse_pattern()
+                }));
+                parser.eat_variant(TokenKind::RightParenthese)?;
+                Ok(Pat::Constructor { name, args })
+            },
+            |parser| {
+                parser.eat_variant(TokenKind::LeftParenthese)?;
+                let mut elements = vec![];
+                let mut trailing_comma = false;
+                if let Some(first) = parser.optional(Parser::parse_pattern) {
+                    elements.push(first);
+                    elements.extend(parser.none_or_more(|parser| {
+                        parser.eat_punctuation(Punctuation::Comma)?;
+                        parser.parse_pattern()
+                    }));
+                    trailing_comma = parser
+                        .eat_punctuation(Punctuation::Comma)
+                        .is_ok();
+                }
+                parser.eat_variant(TokenKind::RightParenthese)?;
+
+                if elements.len() == 1 && !trailing_comma {
+                    Ok(elements.pop().unwrap().pat)
+                } else {
+                    Ok(Pat::Tuple(elements))
+                }
+            },
+            |parser| {
+                parser.parse_ident().map(|name| match name.as_str() {
+                    "_" => Pat::Wildcard,
+                    _ => Pat::Name(name),
+                })
+            },
+        ])?;
+        let span_end = self.previous().source_span().1;
+        let kind = match &pat {
+            Pat::Tuple(_) => SyntaxKind::TuplePattern,
+            _ if parenthesized => SyntaxKind::GroupingPattern,
+            Pat::Wildcard => SyntaxKind::WildcardPattern,
+            Pat::Name(_) => SyntaxKind::NamePattern,
+            Pat::Integer(_) => SyntaxKind::IntegerPattern,
+            Pat::Constructor { .. } => SyntaxKind::ConstructorPattern,
+        };
+        self.finish_node(node, kind);
+
+        Ok(Pattern {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            pat,
+        })
+    }
+
+    /// Parses `name: Type`.
+    pub fn parse_field_decl(&mut self) -> ParserResult<(String, TypeExpression)> {
+        let name = self.parse_ident()?;
+        let ty = self.parse_type_annotation()?;
+        Ok((name, ty))
+    }
+
+    /// Parses a type: `Int`, `Range<Int>`, `[Int]`, `(Int, Bool)` or `(Int, Int) -> Int`.
+    ///
+    /// A parenthesized list followed by an arrow is a function type, otherwise it is a tuple,
+    /// unless it holds a single type without a trailing comma, which only groups it.
+    pub fn parse_type_expr(&mut self) -> ParserResult<TypeExpression> {
+        let node = self.start_node();
+        let span_begin = self.current().source_span().0;
+        let loc = self.current().source_location();
+        let parenthesized = matches!(self.current().kind, TokenKind::LeftParenthese);
+
+        let ty = self.one_of(&[
+            |parser| {
+                parser.eat_variant(TokenKind::LeftParenthese)?;
+                let mut elements = vec![];
+                let mut trailing_comma = false;
+                if let Some(first) = parser.optional(Parser::parse_type_expr) {
+                    elements.push(first);
+                    elements.extend(parser.none_or_more(|parser| {
+                        parser.eat_punctuation(Punctuation::Comma)?;
+                        parser.parse_type_expr()
+                    }));
+                    trailing_comma = parser
+                        .eat_punctuation(Punctuation::Comma)
+                        .is_ok();
+                }
+                parser.eat_variant(TokenKind::RightParenthese)?;
+
+                if parser.eat_variant(TokenKind::Arrow).is_ok() {
+                    let ret = parser.parse_type_expr()?;
+                    return Ok(TypeExpr::Function {
+                        params: elements,
+                        ret: Box::new(ret),
+                    });
+                }
+
+                if elements.len() == 1 && !trailing_comma {
+                    Ok(elements.pop().unwrap().ty)
+                } else {
+                    Ok(TypeExpr::Tuple(elements))
+                }
+            },
+            |parser| {
+                parser.eat_variant(TokenKind::LeftBracket)?;
+                let elem = parser.parse_type_expr()?;
+                parser.eat_variant(TokenKind::RightBracket)?;
+                Ok(TypeExpr::Array(Box::new(elem)))
+            },
+            |parser| {
+                let name = parser.parse_ident()?;
+                parser.eat_if(|token| matches!(token.kind, TokenKind::Operator(Operator::Less)))?;
+                let mut args = vec![parser.parse_type_expr()?];
+                args.extend(parser.none_or_more(|parser| {
+                    parser.eat_punctuation(Punctuation::Comma)?;
+                    parser.parse_type_expr()
+                }));
+                parser
+                    .eat_if(|token| matches!(token.kind, TokenKind::Operator(Operator::Greater)))?;
+                Ok(TypeExpr::Generic { name, args })
+            },
+            |parser| parser.parse_ident().map(TypeExpr::Named),
+        ])?;
+        let span_end = self.previous().source_span().1;
+        let kind = match &ty {
+            TypeExpr::Function { .. } => SyntaxKind::FunctionType,
+            TypeExpr::Tuple(_) => SyntaxKind::TupleType,
+            _ if parenthesized => SyntaxKind::GroupingType,
+            TypeExpr::Named(_) => SyntaxKind::NamedType,
+            TypeExpr::Generic { .. } => SyntaxKind::GenericType,
+            TypeExpr::Array(_) => SyntaxKind::ArrayType,
+        };
+        self.finish_node(node, kind);
+
+        Ok(TypeExpression {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            ty,
+        })
+    }
+
+    pub fn parse_return(&mut self) -> ParserResult<Statement> {
+        let node = self.start_node();
+        let return_keyword = self.eat_keyword(Keyword::Return)?;
+        let span_begin = return_keyword.source_span().0;
+        let loc = return_keyword.source_location();
+
+        let span_end = return_keyword.source_span().1;
+        let value = self.optional(|parser| parser.parse_expression());
+        let span_end = value.as_ref().map_or(span_end, |value| value.source_span().1);
+        self.finish_node(node, SyntaxKind::Return);
+
+        Ok(Statement {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            stmt: Stmt::Return { value },
+        })
+    }
+
+    /// Parses `: NEWLINE INDENT <block> DEDENT` that follows a compound statement header, or
+    /// the `: <simple stmt>; ...` of a body kept on the header's line.
+    pub fn parse_indented_block(&mut self) -> ParserResult<StatementBlock> {
+        self.eat_punctuation(Punctuation::Colon)?;
+        if self.eat_variant(TokenKind::Newline).is_err() {
+            return self.parse_line_block();
+        }
+        self.eat_variant(TokenKind::Indent)?;
+
+        let body = self.parse_stmt_block()?;
+
+        self.eat_variant(TokenKind::Dendent)?;
+
+        Ok(body)
+    }
+
+    /// Parses simple statements separated by `;` up to the end of the line.
+    fn parse_line_block(&mut self) -> ParserResult<StatementBlock> {
+        let node = self.start_node();
+        let mut stmts = vec![];
+        loop {
+            stmts.push(self.parse_simple_stmt()?);
+            if self.eat_terminator()? {
+                break;
+            }
+        }
+        self.finish_node(node, SyntaxKind::Block);
+
+        let loc = stmts[0].source_location();
+        let span = (stmts[0].source_span().0, stmts[stmts.len() - 1].source_span().1);
+        Ok(StatementBlock {
+            id: self.node_id(),
+            loc,
+            span,
+            stmts,
         })
     }
 
     pub fn parse_name_decl(&mut self) -> ParserResult<Statement> {
-        let let_keyword = self.eat_variant(TokenKind::Keyword(Keyword::Let))?;
+        let node = self.start_node();
+        let let_keyword = self.eat_keyword(Keyword::Let)?;
         let span_begin = let_keyword.source_span().0;
         let loc = let_keyword.source_location();
 
-        let name = self.eat(|token| match token {
-            Token {
-                kind: TokenKind::Identifier(name),
-                ..
-            } => Ok(name.to_owned()),
-            _ => Err(ParserFault { loc: token.loc }),
-        })?;
+        let pattern = self.parse_pattern()?;
+        let ty = self.optional(Parser::parse_type_annotation);
 
         self.eat_if(|token| matches!(token.kind, TokenKind::Operator(Operator::Equals)))?;
 
         let value = self.parse_expression()?;
         let span_end = value.source_span().1;
 
+        let (stmt, kind) = match pattern.pat {
+            Pat::Name(name) => (
+                Stmt::NameDeclaration { name, ty, value },
+                SyntaxKind::NameDeclaration,
+            ),
+            _ => (
+                Stmt::Destructure { pattern, ty, value },
+                SyntaxKind::Destructure,
+            ),
+        };
+        self.finish_node(node, kind);
+
         Ok(Statement {
-            loc: loc,
+            id: self.node_id(),
+            loc,
             span: (span_begin, span_end),
-            stmt: Stmt::NameDeclaration { name, value },
+            stmt,
         })
     }
 
+    /// Parses any expression, from the loosest to the tightest binding:
+    ///
+    /// 1. lambdas `\x -> body`, whose body extends as far as possible
+    /// 2. ranges `start..end`
+    /// 3. binary operators, ordered among themselves by [`precedence_of`], comparisons chaining
+    ///    as told by [`is_comparison`]
+    /// 4. application by juxtaposition `f a b`, with the callee and arguments being postfix
+    ///    expressions
+    /// 5. postfix `.field`, `xs[i]` and `f(a, b)`, the last two only without a space before the
+    ///    bracket
+    ///
+    /// So `f x.y + g(1) 2` is `(f (x.y)) + ((g(1)) 2)`.
     pub fn parse_expression(&mut self) -> ParserResult<Expression> {
-        self.one_of(&[Self::parse_function_application, Self::parse_binop_expr])
+        self.memoized(Rule::Expression, |parser| {
+            parser.one_of(&[Self::parse_lambda, Self::parse_range, Self::parse_binop_expr])
+        })
+    }
+
+    /// Parses `start..end`, the bounds are anything but another range or a lambda.
+    pub fn parse_range(&mut self) -> ParserResult<Expression> {
+        let node = self.start_node();
+        let start = self.parse_binop_expr()?;
+        self.eat_variant(TokenKind::DoubleDot)?;
+        let end = self.parse_binop_expr()?;
+        self.finish_node(node, SyntaxKind::Range);
+
+        Ok(Expression {
+            id: self.node_id(),
+            loc: start.source_location(),
+            span: (start.source_span().0, end.source_span().1),
+            expr: Expr::Range(RangeExpr {
+                start: Box::new(start),
+                end: Box::new(end),
+            }),
+        })
+    }
+
+    pub fn parse_lambda(&mut self) -> ParserResult<Expression> {
+        let node = self.start_node();
+        let backslash = self.eat_variant(TokenKind::Backslash)?;
+        let span_begin = backslash.source_span().0;
+        let loc = backslash.source_location();
+
+        let params = self.one_or_more(Parser::parse_ident)?;
+        self.eat_variant(TokenKind::Arrow)?;
+
+        let body = self.parse_expression()?;
+        let span_end = body.source_span().1;
+        self.finish_node(node, SyntaxKind::Lambda);
+
+        Ok(Expression {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            expr: Expr::Lambda(Lambda {
+                params,
+                body: Box::new(body),
+            }),
+        })
     }
 
     pub fn parse_primary_expression(&mut self) -> ParserResult<Expression> {
-        self.one_of(&[
+        self.memoized(Rule::Primary, Self::parse_postfix_chain)
+    }
+
+    fn parse_postfix_chain(&mut self) -> ParserResult<Expression> {
+        let node = self.start_node();
+        let mut expr = self.one_of(&[
+            Parser::parse_record,
+            Parser::parse_array,
             Parser::parse_integer,
             Parser::parse_name,
             Parser::parse_group,
-        ])
+        ])?;
+
+        loop {
+            let loc = expr.source_location();
+            let span_begin = expr.source_span().0;
+
+            let (suffix, kind) = if let Some(field) = self.optional(|parser| {
+                parser.eat_variant(TokenKind::Dot)?;
+                parser.parse_ident()
+            }) {
+                let suffix = Expr::FieldAccess(FieldAccess {
+                    base: Box::new(expr),
+                    field,
+                });
+                (suffix, SyntaxKind::FieldAccess)
+            } else if let Some(args) = self.optional(|parser| {
+                // `f(a, b)` calls `f` while `f (a, b)` applies `f` to a tuple
+                if !parser.is_adjacent() {
+                    return Err(ParserFault {
+                        loc: parser.current().source_location(),
+                    });
+                }
+                parser.eat_variant(TokenKind::LeftParenthese)?;
+                let mut args = vec![];
+                if let Some(first) = parser.optional(Parser::parse_expression) {
+                    args.push(first);
+                    args.extend(parser.none_or_more(|parser| {
+                        parser.eat_punctuation(Punctuation::Comma)?;
+                        parser.parse_expression()
+                    }));
+                }
+                parser.eat_variant(TokenKind::RightParenthese)?;
+                Ok(args)
+            }) {
+                let suffix = Expr::FunctionApplication(FunctionApplication {
+                    func: Box::new(expr),
+                    args,
+                });
+                (suffix, SyntaxKind::FunctionApplication)
+            } else if let Some(index) = self.optional(|parser| {
+                // `xs[i]` indexes while `f [a, b]` applies `f` to an array
+                if !parser.is_adjacent() {
+                    return Err(ParserFault {
+                        loc: parser.current().source_location(),
+                    });
+                }
+                parser.eat_variant(TokenKind::LeftBracket)?;
+                let index = parser.parse_expression()?;
+                parser.eat_variant(TokenKind::RightBracket)?;
+                Ok(index)
+            }) {
+                let suffix = Expr::Index(IndexExpr {
+                    base: Box::new(expr),
+                    index: Box::new(index),
+                });
+                (suffix, SyntaxKind::Index)
+            } else {
+                break;
+            };
+            // every suffix wraps the expression before it, so all of them start together
+            self.finish_node(node, kind);
+
+            expr = Expression {
+                id: self.node_id(),
+                loc,
+                span: (span_begin, self.previous().source_span().1),
+                expr: suffix,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses `[element, ...]`.
+    pub fn parse_array(&mut self) -> ParserResult<Expression> {
+        let node = self.start_node();
+        let left_bracket = self.eat_variant(TokenKind::LeftBracket)?;
+        let span_begin = left_bracket.source_span().0;
+        let loc = left_bracket.source_location();
+
+        let mut elements = vec![];
+        if let Some(first) = self.optional(Parser::parse_expression) {
+            elements.push(first);
+            elements.extend(self.none_or_more(|parser| {
+                parser.eat_punctuation(Punctuation::Comma)?;
+                parser.parse_expression()
+            }));
+        }
+
+        let span_end = self.eat_variant(TokenKind::RightBracket)?.source_span().1;
+        self.finish_node(node, SyntaxKind::Array);
+
+        Ok(Expression {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            expr: Expr::Array(ArrayExpr { elements }),
+        })
+    }
+
+    /// Parses `Name { field: value, ... }`.
+    pub fn parse_record(&mut self) -> ParserResult<Expression> {
+        let node = self.start_node();
+        let (loc, span_begin) = (self.current().source_location(), self.current().source_span().0);
+        let name = self.parse_ident()?;
+        self.eat_variant(TokenKind::LeftBrace)?;
+
+        let mut fields = vec![];
+        if let Some(first) = self.optional(Parser::parse_field_init) {
+            fields.push(first);
+            fields.extend(self.none_or_more(|parser| {
+                parser.eat_punctuation(Punctuation::Comma)?;
+                parser.parse_field_init()
+            }));
+        }
+
+        let span_end = self.eat_variant(TokenKind::RightBrace)?.source_span().1;
+        self.finish_node(node, SyntaxKind::Record);
+
+        Ok(Expression {
+            id: self.node_id(),
+            loc,
+            span: (span_begin, span_end),
+            expr: Expr::Record(RecordExpr { name, fields }),
+        })
+    }
+
+    /// Parses `field: value`.
+    pub fn parse_field_init(&mut self) -> ParserResult<(String, Expression)> {
+        let name = self.parse_ident()?;
+        self.eat_punctuation(Punctuation::Colon)?;
+        let value = self.parse_expression()?;
+        Ok((name, value))
     }
 
     pub fn parse_group(&mut self) -> ParserResult<Expression> {
+        let node = self.start_node();
         let _first = self.eat_variant(TokenKind::LeftParenthese)?;
         let span_begin = _first.source_span().0;
         let loc = _first.source_location();
-        let expr = self.parse_expression()?;
+
+        let mut elements = vec![];
+        let mut trailing_comma = false;
+        if let Some(first) = self.optional(Parser::parse_expression) {
+            elements.push(first);
+            elements.extend(self.none_or_more(|parser| {
+                parser.eat_punctuation(Punctuation::Comma)?;
+                parser.parse_expression()
+            }));
+            trailing_comma = self
+                .eat_punctuation(Punctuation::Comma)
+                .is_ok();
+        }
+
         let _last = self.eat_variant(TokenKind::RightParenthese)?;
         let span_end = _last.source_span().1;
 
+        // `(x)` only groups, `()`, `(x,)` and `(x, y)` are tuples
+        let (expr, kind) = if elements.len() == 1 && !trailing_comma {
+            let expr = Expr::Grouping {
+                expr: Box::new(elements.pop().unwrap()),
+            };
+            (expr, SyntaxKind::Grouping)
+        } else {
+            (Expr::Tuple(TupleExpr { elements }), SyntaxKind::Tuple)
+        };
+        self.finish_node(node, kind);
+
         Ok(Expression {
+            id: self.node_id(),
             span: (span_begin, span_end),
-            expr: Expr::Grouping {
-                expr: Box::new(expr),
-            },
+            expr,
             loc,
         })
     }
 
+    /// Parses `func arg ...`, or just `func` when no arguments follow it.
     pub fn parse_function_application(&mut self) -> ParserResult<Expression> {
-        let func = self.parse_name()?;
-        let args = self.one_or_more(|p| {
-            p.one_of(&[
-                Parser::parse_integer,
-                Parser::parse_name,
-                Parser::parse_group,
-            ])
-        })?;
+        self.memoized(Rule::Application, |parser| {
+            let node = parser.start_node();
+            let func = parser.parse_primary_expression()?;
+            let args = parser.none_or_more(Parser::parse_primary_expression);
+            if args.is_empty() {
+                return Ok(func);
+            }
+            parser.finish_node(node, SyntaxKind::FunctionApplication);
 
-        Ok(Expression {
-            loc: func.source_location(),
-            span: (func.source_span().0, args.last().unwrap().span.1),
-            expr: Expr::FunctionApplication(FunctionApplication {
-                func: Box::new(func),
-                args: args,
-            }),
+            Ok(Expression {
+                id: parser.node_id(),
+                loc: func.source_location(),
+                span: (func.source_span().0, args.last().unwrap().span.1),
+                expr: Expr::FunctionApplication(FunctionApplication {
+                    func: Box::new(func),
+                    args,
+                }),
+            })
         })
     }
 
     pub fn parse_binop_expr(&mut self) -> ParserResult<Expression> {
-        let mut output_stack: Vec<Expression> = vec![];
+        self.memoized(Rule::Binop, Self::parse_binop_chain)
+    }
+
+    fn parse_binop_chain(&mut self) -> ParserResult<Expression> {
+        // the operands along with where their syntax starts
+        let mut output_stack: Vec<(Expression, usize)> = vec![];
         let mut operator_stack: Vec<Operator> = vec![];
 
-        let primary_starter = self.parse_primary_expression()?;
-        output_stack.push(primary_starter);
+        let node = self.start_node();
+        let primary_starter = self.parse_function_application()?;
+        output_stack.push((primary_starter, node));
 
-        while let Ok(binop) = self.eat_if(Token::is_binop) {
-            let op = match &binop.kind {
+        while self.current().is_binop() {
+            let op = match &self.current().kind {
                 TokenKind::Operator(op) => *op,
                 _ => unreachable!(),
             };
 
-            if !operator_stack.is_empty() {
-                if precedence_of(&op) >= precedence_of(&operator_stack.last().unwrap()) {
-                    let op = operator_stack.pop().unwrap();
-                    let rhs = output_stack.pop().unwrap();
-                    let lhs = output_stack.pop().unwrap();
-
-                    let span = (lhs.source_span().0, rhs.source_span().1);
-                    let loc = lhs.source_location();
-
-                    output_stack.push(Expression {
-                        loc,
-                        span,
-                        expr: Expr::Binop(BinopExpr {
-                            op,
-                            lhs: Box::new(lhs),
-                            rhs: Box::new(rhs),
-                        }),
-                    });
+            // every pending operator binding at least as tightly is reduced first,
+            // so that `a * b + c * d - e` keeps `-` applied to the whole sum
+            while let Some(top) = operator_stack.last() {
+                if precedence_of(&op) < precedence_of(top) {
+                    break;
                 }
+                let top = operator_stack.pop().unwrap();
+                self.reduce_binop(&mut output_stack, top);
             }
 
+            self.bump();
             operator_stack.push(op);
-            output_stack.push(self.parse_primary_expression()?);
+            let node = self.start_node();
+            if op == Operator::Equals {
+                // whatever is assigned extends as far as possible, be it a lambda, a range
+                // or another assignment, which makes `=` nest to the right
+                output_stack.push((self.parse_expression()?, node));
+                break;
+            }
+            output_stack.push((self.parse_function_application()?, node));
         }
 
-        while !operator_stack.is_empty() {
-            let op = operator_stack.pop().unwrap();
-            let rhs = output_stack.pop().unwrap();
-            let lhs = output_stack.pop().unwrap();
+        while let Some(op) = operator_stack.pop() {
+            self.reduce_binop(&mut output_stack, op);
+        }
 
-            let span = (lhs.source_span().0, rhs.source_span().1);
-            let loc = lhs.source_location();
+        let (expr, _) = output_stack.pop().unwrap();
 
-            output_stack.push(Expression {
+        Ok(expr)
+    }
+
+    /// Replaces the two operands on top of `output_stack` by their operation with `op`.
+    fn reduce_binop(&mut self, output_stack: &mut Vec<(Expression, usize)>, op: Operator) {
+        let (rhs, _) = output_stack.pop().unwrap();
+        let (lhs, node) = output_stack.pop().unwrap();
+
+        let span = (lhs.source_span().0, rhs.source_span().1);
+        let loc = lhs.source_location();
+        self.finish_node(node, SyntaxKind::Binop);
+
+        output_stack.push((
+            Expression {
+                id: self.node_id(),
                 loc,
                 span,
                 expr: Expr::Binop(BinopExpr {
@@ -282,16 +1362,53 @@ impl Parser {
                     lhs: Box::new(lhs),
                     rhs: Box::new(rhs),
                 }),
-            });
+            },
+            node,
+        ));
+    }
+
+    /// Parses a whole file, which may hold no statements at all. Everything up to the end of
+    /// input must be consumed. When some is left over, the fault is at the furthest token any
+    /// statement got to before failing, e.g. at the `)` in `let x = 1 )`.
+    pub fn parse_module(&mut self) -> ParserResult<StatementBlock> {
+        // a lexer giving up before the end leaves the stream without its end marker
+        match self.tokens.last() {
+            Some(Token {
+                kind: TokenKind::End,
+                ..
+            }) => {}
+            last => {
+                return Err(ParserFault {
+                    loc: last.map(Token::source_location).unwrap_or_default(),
+                })
+            }
         }
 
-        let expr = output_stack.pop().unwrap();
+        let (loc, begin) = (self.current().source_location(), self.current().source_span().0);
+        let node = self.start_node();
+        let stmts = self.none_or_more(Parser::parse_stmt);
+        self.finish_node(node, SyntaxKind::Block);
+        let span = match (stmts.first(), stmts.last()) {
+            (Some(first), Some(last)) => (first.source_span().0, last.source_span().1),
+            _ => (begin, begin),
+        };
+        let loc = stmts.first().map_or(loc, SourceObject::source_location);
+        if let Err(fault) = self.eat_variant(TokenKind::End) {
+            return Err(self.furthest_fault(fault));
+        }
 
-        Ok(expr)
+        Ok(StatementBlock {
+            id: self.node_id(),
+            loc,
+            span,
+            stmts,
+        })
     }
 
     pub fn parse_stmt_block(&mut self) -> ParserResult<StatementBlock> {
+        let node = self.start_node();
         let stmts = self.one_or_more(Parser::parse_stmt)?;
+        self.finish_node(node, SyntaxKind::Block);
 
         if !stmts.is_empty() {
             // TODO: refactor the unwraps
@@ -300,6 +1417,7 @@ impl Parser {
             let end_span = stmts.last().unwrap().source_span().1;
 
             Ok(StatementBlock {
+                id: self.node_id(),
                 loc,
                 span: (begin_span, end_span),
                 stmts,
@@ -329,7 +1447,12 @@ impl Parser {
             (this.source_location(), this.source_span())
         };
 
-        self.parse_ident().map(|ident| Expression {
+        let node = self.start_node();
+        let ident = self.parse_ident()?;
+        self.finish_node(node, SyntaxKind::Name);
+
+        Ok(Expression {
+            id: self.node_id(),
             loc,
             span,
             expr: Expr::Name(ident),
@@ -337,7 +1460,9 @@ impl Parser {
     }
 
     pub fn parse_integer(&mut self) -> ParserResult<Expression> {
-        self.eat_variant(TokenKind::Integer("".to_string()))
+        let node = self.start_node();
+        let (loc, span, int) = self
+            .eat_variant(TokenKind::Integer("".to_string()))
             .map(|token| {
                 if let Token {
                     kind: TokenKind::Integer(int),
@@ -346,14 +1471,18 @@ impl Parser {
                     ..
                 } = token
                 {
-                    Expression {
-                        span: span.to_owned(),
-                        loc: loc.to_owned(),
-                        expr: Expr::IntegerLiteral(int.to_owned()),
-                    }
+                    (loc.to_owned(), span.to_owned(), int.to_owned())
                 } else {
                     unreachable!();
                 }
-            })
+            })?;
+        self.finish_node(node, SyntaxKind::IntegerLiteral);
+
+        Ok(Expression {
+            id: self.node_id(),
+            span,
+            loc,
+            expr: Expr::IntegerLiteral(int),
+        })
     }
 }
@@ -0,0 +1,584 @@
+use std::fmt::{self, Display};
+
+use lex::{Operator, SourceObject};
+
+use crate::{
+    is_comparison, precedence_of, BinopExpr, Expr, Expression, FunctionApplication, Pat, Pattern,
+    Statement, StatementBlock, Stmt, TypeExpr, TypeExpression,
+};
+
+const INDENT: &str = "    ";
+
+/// How loosely an expression may bind where it is printed, anything looser gets parenthesized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Lambda,
+    Range,
+    Binop,
+    Application,
+    Primary,
+}
+
+/// Where a statement printed by [`Printer::block`] came from and where it went.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceMapping {
+    /// Offset of the statement in the source it was parsed from.
+    pub source: usize,
+    /// Offset of the line holding the statement in the printed output.
+    pub output: usize,
+    pub indent: usize,
+}
+
+/// Prints the AST back to source, adding only the parentheses that [`precedence_of`] and
+/// the expression grammar require, so that parsing the output gives back the same tree.
+#[derive(Debug, Clone)]
+pub struct Printer {
+    /// Lines that would run past this column are broken up: bracketed lists get one item
+    /// per line, operator chains one operand per line and applications turn into calls.
+    pub width: usize,
+    /// Whether the parentheses of grouping nodes are printed, or left for the printer to
+    /// decide upon.
+    pub keep_groupings: bool,
+    indent: usize,
+    out: String,
+    source_map: Vec<SourceMapping>,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+
+impl Printer {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            keep_groupings: true,
+            indent: 0,
+            out: String::new(),
+            source_map: vec![],
+        }
+    }
+
+    pub fn output(&self) -> &str {
+        &self.out
+    }
+
+    pub fn source_map(&self) -> &[SourceMapping] {
+        &self.source_map
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn start_line(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.out.push('\n');
+    }
+
+    fn column(&self) -> usize {
+        self.out.len() - self.out.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Whether what `print` writes fits on the current line, printed without breaks.
+    fn fits(&self, print: impl FnOnce(&mut Printer)) -> bool {
+        if self.width == usize::MAX {
+            return true;
+        }
+        let mut flat = Printer {
+            keep_groupings: self.keep_groupings,
+            ..Printer::new(usize::MAX)
+        };
+        print(&mut flat);
+        self.column() + flat.out.len() <= self.width
+    }
+
+    /// Whether the output is within brackets, where lines may break anywhere.
+    fn in_brackets(&self) -> bool {
+        let mut depth = 0usize;
+        for c in self.out.chars() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        depth > 0
+    }
+
+    pub fn block(&mut self, block: &StatementBlock) {
+        for stmt in &block.stmts {
+            self.source_map.push(SourceMapping {
+                source: stmt.source_span().0,
+                output: self.out.len(),
+                indent: self.indent,
+            });
+            self.start_line();
+            self.stmt(stmt);
+        }
+    }
+
+    fn body(&mut self, block: &StatementBlock) {
+        self.write(":");
+        self.end_line();
+        self.indent += 1;
+        self.block(block);
+        self.indent -= 1;
+    }
+
+    /// Prints `stmt` from the current column on, along with the line break ending it.
+    pub fn stmt(&mut self, stmt: &Statement) {
+        match &stmt.stmt {
+            Stmt::NameDeclaration { name, ty, value } => {
+                self.write(&format!("let {name}"));
+                self.annotation(ty.as_ref());
+                self.write(" = ");
+                self.expr(value);
+                self.end_line();
+            }
+            Stmt::Destructure { pattern, ty, value } => {
+                self.write(&format!("let {pattern}"));
+                self.annotation(ty.as_ref());
+                self.write(" = ");
+                self.expr(value);
+                self.end_line();
+            }
+            Stmt::WhileStmt { pred, body } => {
+                self.write("while ");
+                self.expr(pred);
+                self.body(body);
+            }
+            Stmt::IfStmt {
+                pred,
+                body,
+                elifs,
+                else_body,
+            } => {
+                self.write("if ");
+                self.expr(pred);
+                self.body(body);
+                for (pred, body) in elifs {
+                    self.start_line();
+                    self.write("elif ");
+                    self.expr(pred);
+                    self.body(body);
+                }
+                if let Some(body) = else_body {
+                    self.start_line();
+                    self.write("else");
+                    self.body(body);
+                }
+            }
+            Stmt::ForStmt {
+                name,
+                iterable,
+                body,
+            } => {
+                self.write(&format!("for {name} in "));
+                self.expr(iterable);
+                self.body(body);
+            }
+            Stmt::Break => {
+                self.write("break");
+                self.end_line();
+            }
+            Stmt::Continue => {
+                self.write("continue");
+                self.end_line();
+            }
+            Stmt::FunctionDef {
+                name,
+                params,
+                ret,
+                body,
+            } => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|(name, ty)| match ty {
+                        Some(ty) => format!("{name}: {ty}"),
+                        None => name.to_owned(),
+                    })
+                    .collect();
+                self.write(&format!("fn {name}({})", params.join(", ")));
+                if let Some(ret) = ret {
+                    self.write(&format!(" -> {ret}"));
+                }
+                self.body(body);
+            }
+            Stmt::StructDef { name, fields } => {
+                self.write(&format!("struct {name}:"));
+                self.end_line();
+                self.indent += 1;
+                for (field, ty) in fields {
+                    self.start_line();
+                    self.write(&format!("{field}: {ty}"));
+                    self.end_line();
+                }
+                self.indent -= 1;
+            }
+            Stmt::EnumDef { name, variants } => {
+                let variants: Vec<String> = variants
+                    .iter()
+                    .map(|(variant, fields)| match fields.as_slice() {
+                        [] => variant.to_owned(),
+                        fields => format!("{variant}({})", comma_separated(fields)),
+                    })
+                    .collect();
+                self.write(&format!("enum {name}: {}", variants.join(" | ")));
+                self.end_line();
+            }
+            Stmt::MatchStmt { scrutinee, arms } => {
+                self.write("match ");
+                self.expr(scrutinee);
+                self.write(":");
+                self.end_line();
+                self.indent += 1;
+                for (pattern, body) in arms {
+                    self.start_line();
+                    self.write(&pattern.to_string());
+                    self.body(body);
+                }
+                self.indent -= 1;
+            }
+            Stmt::Return { value } => {
+                self.write("return");
+                if let Some(value) = value {
+                    self.write(" ");
+                    self.expr(value);
+                }
+                self.end_line();
+            }
+            Stmt::Import { path } => {
+                self.write(&format!("import {}", path.join(".")));
+                self.end_line();
+            }
+            Stmt::FromImport { path, names } => {
+                self.write(&format!("from {} import {}", path.join("."), names.join(", ")));
+                self.end_line();
+            }
+            Stmt::Public(item) => {
+                self.write("pub ");
+                self.stmt(item);
+            }
+            Stmt::Expression(expr) => {
+                self.expr(expr);
+                self.end_line();
+            }
+        }
+    }
+
+    fn annotation(&mut self, ty: Option<&TypeExpression>) {
+        if let Some(ty) = ty {
+            self.write(&format!(": {ty}"));
+        }
+    }
+
+    pub fn expr(&mut self, expr: &Expression) {
+        self.expr_at(&expr.expr, Level::Lambda);
+    }
+
+    fn ungrouped<'e>(&self, expr: &'e Expr) -> &'e Expr {
+        match expr {
+            Expr::Grouping { expr } if !self.keep_groupings => self.ungrouped(&expr.expr),
+            expr => expr,
+        }
+    }
+
+    /// Prints `expr` where nothing looser than `level` may appear unparenthesized.
+    fn expr_at(&mut self, expr: &Expr, level: Level) {
+        let expr = self.ungrouped(expr);
+        if level_of(expr) < level {
+            self.write("(");
+            self.expr_inner(expr);
+            self.write(")");
+        } else {
+            self.expr_inner(expr);
+        }
+    }
+
+    /// Prints an operand of the binary operator `op`. Operators associate to the left, so a
+    /// looser operator needs parentheses on the left, and one at least as loose on the right.
+    /// A parenthesized comparison on the left of another one stays so, lest it become a chain.
+    /// Assignments are the exception, they take any expression on the right and nest there.
+    fn operand(&mut self, operand: &Expr, op: Operator, is_rhs: bool) {
+        let grouped = matches!(operand, Expr::Grouping { .. });
+        let operand = self.ungrouped(operand);
+        let precedence = precedence_of(&op);
+        if is_rhs && op == Operator::Equals {
+            self.expr_at(operand, Level::Lambda);
+            return;
+        }
+        let needs_parens = match operand {
+            Expr::Binop(inner) if is_rhs || op == Operator::Equals => {
+                precedence_of(&inner.op) >= precedence
+            }
+            Expr::Binop(inner) if grouped && is_comparison(&op) && is_comparison(&inner.op) => true,
+            Expr::Binop(inner) => precedence_of(&inner.op) > precedence,
+            _ => false,
+        };
+        if needs_parens {
+            self.write("(");
+            self.expr_inner(operand);
+            self.write(")");
+        } else {
+            self.expr_at(operand, Level::Binop);
+        }
+    }
+
+    fn expr_inner(&mut self, expr: &Expr) {
+        match expr {
+            Expr::IntegerLiteral(int) => self.write(int),
+            Expr::Name(name) => self.write(name),
+            Expr::Binop(binop) => {
+                if binop.op == Operator::Equals || self.fits(|flat| flat.expr_inner(expr)) {
+                    self.operand(&binop.lhs.expr, binop.op, false);
+                    let op: &str = binop.op.into();
+                    self.write(&format!(" {op} "));
+                    self.operand(&binop.rhs.expr, binop.op, true);
+                } else if self.in_brackets() {
+                    self.indent += 1;
+                    self.broken_binop(binop);
+                    self.indent -= 1;
+                } else {
+                    // lines only break within brackets, so the chain gets parentheses of its own
+                    self.write("(");
+                    self.end_line();
+                    self.indent += 1;
+                    self.start_line();
+                    self.broken_binop(binop);
+                    self.end_line();
+                    self.indent -= 1;
+                    self.start_line();
+                    self.write(")");
+                }
+            }
+            Expr::FunctionApplication(application) => self.application(application, false),
+            Expr::Grouping { expr } => {
+                self.write("(");
+                self.expr(expr);
+                self.write(")");
+            }
+            Expr::Lambda(lambda) => {
+                self.write(&format!("\\{} -> ", lambda.params.join(" ")));
+                self.expr(&lambda.body);
+            }
+            Expr::Range(range) => {
+                self.expr_at(&range.start.expr, Level::Binop);
+                self.write("..");
+                self.expr_at(&range.end.expr, Level::Binop);
+            }
+            Expr::Record(record) => {
+                self.write(&format!("{} ", record.name));
+                if record.fields.is_empty() {
+                    self.write("{}");
+                } else {
+                    self.list(("{ ", "{"), (" }", "}"), &record.fields, false, |printer, (name, value)| {
+                        printer.write(&format!("{name}: "));
+                        printer.expr(value);
+                    });
+                }
+            }
+            Expr::FieldAccess(access) => {
+                self.postfix_base(&access.base.expr);
+                self.write(&format!(".{}", access.field));
+            }
+            Expr::Array(array) => {
+                self.list(("[", "["), ("]", "]"), &array.elements, false, Printer::expr);
+            }
+            Expr::Index(index) => {
+                self.postfix_base(&index.base.expr);
+                self.write("[");
+                self.expr(&index.index);
+                self.write("]");
+            }
+            Expr::Tuple(tuple) => {
+                // a single element keeps its comma, or it would read back as a grouping
+                let single = tuple.elements.len() == 1;
+                self.list(("(", "("), (")", ")"), &tuple.elements, single, Printer::expr);
+            }
+        }
+    }
+
+    /// Prints the operands of the chain of operators as loose as the one of `binop`, each
+    /// but the first on a line of its own behind its operator, indented to the current level.
+    fn broken_binop(&mut self, binop: &BinopExpr) {
+        let precedence = precedence_of(&binop.op);
+        let mut chain = vec![(binop.op, &binop.rhs.expr)];
+        let mut first = &binop.lhs.expr;
+        while let Expr::Binop(lhs) = first {
+            if precedence_of(&lhs.op) != precedence {
+                break;
+            }
+            chain.push((lhs.op, &lhs.rhs.expr));
+            first = &lhs.lhs.expr;
+        }
+
+        self.operand(first, chain.last().unwrap().0, false);
+        for (op, operand) in chain.into_iter().rev() {
+            self.end_line();
+            self.start_line();
+            let text: &str = op.into();
+            self.write(&format!("{text} "));
+            self.operand(operand, op, true);
+        }
+    }
+
+    /// Prints `f a b`, or `f(a, b)` when `call` asks for it, when it would not fit on the
+    /// line, or when an argument would need parentheses the tree does not have, which would
+    /// read back as groupings.
+    fn application(&mut self, application: &FunctionApplication, call: bool) {
+        self.postfix_base(&application.func.expr);
+        let args = &application.args;
+        let juxtaposed = |flat: &mut Printer| {
+            for arg in args {
+                flat.write(" ");
+                flat.expr_at(&arg.expr, Level::Primary);
+            }
+        };
+        if call
+            || args.is_empty()
+            || args.iter().any(|arg| level_of(&arg.expr) < Level::Primary)
+            || !self.fits(juxtaposed)
+        {
+            self.list(("(", "("), (")", ")"), args, false, Printer::expr);
+        } else {
+            for arg in args {
+                self.write(" ");
+                self.expr_at(&arg.expr, Level::Primary);
+            }
+        }
+    }
+
+    /// Prints what is called, indexed or has a field taken, where an application stays
+    /// without parentheses in its call form: `f(a)[0]` rather than `(f a)[0]`.
+    fn postfix_base(&mut self, expr: &Expr) {
+        match expr {
+            Expr::FunctionApplication(application) => self.application(application, true),
+            _ => self.expr_at(expr, Level::Primary),
+        }
+    }
+
+    /// Prints a comma separated list on the current line when it fits, one item per line
+    /// otherwise. `open` and `close` are the (flat, broken) forms of the delimiters.
+    fn list<T>(
+        &mut self,
+        open: (&str, &str),
+        close: (&str, &str),
+        items: &[T],
+        trailing_comma: bool,
+        item: fn(&mut Printer, &T),
+    ) {
+        let flat = |flat: &mut Printer| {
+            flat.write(open.0);
+            for (i, element) in items.iter().enumerate() {
+                if i > 0 {
+                    flat.write(", ");
+                }
+                item(flat, element);
+            }
+            if trailing_comma {
+                flat.write(",");
+            }
+            flat.write(close.0);
+        };
+
+        if items.is_empty() || self.fits(flat) {
+            flat(self);
+            return;
+        }
+
+        self.write(open.1);
+        self.end_line();
+        self.indent += 1;
+        for (i, element) in items.iter().enumerate() {
+            self.start_line();
+            item(self, element);
+            if i + 1 < items.len() || trailing_comma {
+                self.write(",");
+            }
+            self.end_line();
+        }
+        self.indent -= 1;
+        self.start_line();
+        self.write(close.1);
+    }
+}
+
+fn level_of(expr: &Expr) -> Level {
+    match expr {
+        Expr::Lambda(_) => Level::Lambda,
+        Expr::Range(_) => Level::Range,
+        Expr::Binop(_) => Level::Binop,
+        Expr::FunctionApplication(application) if !application.args.is_empty() => {
+            Level::Application
+        }
+        _ => Level::Primary,
+    }
+}
+
+fn comma_separated(items: &[impl Display]) -> String {
+    items.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+}
+
+impl Display for StatementBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer::default();
+        printer.block(self);
+        f.write_str(printer.output())
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer::default();
+        printer.stmt(self);
+        f.write_str(printer.output())
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer::default();
+        printer.expr(self);
+        f.write_str(printer.output())
+    }
+}
+
+impl Display for TypeExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.ty {
+            TypeExpr::Named(name) => write!(f, "{name}"),
+            TypeExpr::Generic { name, args } => write!(f, "{name}<{}>", comma_separated(args)),
+            TypeExpr::Function { params, ret } => {
+                write!(f, "({}) -> {ret}", comma_separated(params))
+            }
+            TypeExpr::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            TypeExpr::Tuple(elements) => write!(f, "({})", comma_separated(elements)),
+            TypeExpr::Array(element) => write!(f, "[{element}]"),
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pat {
+            Pat::Wildcard => write!(f, "_"),
+            Pat::Name(name) | Pat::Integer(name) => write!(f, "{name}"),
+            Pat::Constructor { name, args } if args.is_empty() => write!(f, "{name}"),
+            Pat::Constructor { name, args } => write!(f, "{name}({})", comma_separated(args)),
+            Pat::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            Pat::Tuple(elements) => write!(f, "({})", comma_separated(elements)),
+        }
+    }
+}
@@ -0,0 +1,669 @@
+//! Lossless syntax tree in the style of rowan.
+//!
+//! Green nodes are immutable, position independent and only know their kind, text length and
+//! children. Red nodes ([`SyntaxNode`], [`SyntaxToken`]) are built on demand on top of them and
+//! know their parent and offset. Every byte of the source, whitespace and comments included,
+//! belongs to exactly one token, so the text of the root is the text of the file.
+//!
+//! The parser builds the green tree as it goes, along with the AST, and the typed views of
+//! [`AstNode`] read what they give access to off the tree.
+
+use std::{fmt, rc::Rc};
+
+use lex::{
+    indented_tokens, omitted_spaces, logical_lines, SourceSpan, Token, TokenKind, TokenStream,
+};
+
+use crate::{
+    ast::StatementBlock,
+    parser::{Parser, SyntaxError},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // tokens
+    Whitespace,
+    Newline,
+    Comment,
+    Keyword,
+    Identifier,
+    Integer,
+    Operator,
+    CompoundOperator,
+    Punctuation,
+    LeftParenthese,
+    RightParenthese,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Backslash,
+    Arrow,
+    DoubleDot,
+    Dot,
+    Pipe,
+
+    // nodes
+    SourceFile,
+    Block,
+    NameDeclaration,
+    Destructure,
+    While,
+    If,
+    For,
+    Break,
+    Continue,
+    FunctionDef,
+    StructDef,
+    EnumDef,
+    Match,
+    Return,
+    Import,
+    FromImport,
+    Public,
+    ExpressionStmt,
+    IntegerLiteral,
+    Binop,
+    Name,
+    FunctionApplication,
+    Grouping,
+    Lambda,
+    Range,
+    Record,
+    FieldAccess,
+    Array,
+    Index,
+    Tuple,
+    WildcardPattern,
+    NamePattern,
+    IntegerPattern,
+    ConstructorPattern,
+    TuplePattern,
+    /// `(pattern)`, which only groups.
+    GroupingPattern,
+    NamedType,
+    GenericType,
+    FunctionType,
+    TupleType,
+    ArrayType,
+    /// `(Type)`, which only groups.
+    GroupingType,
+}
+
+impl SyntaxKind {
+    /// Tokens without any meaning to the parser.
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+
+    /// The kind of the token covering the text of a `kind` token, `None` for the indentation
+    /// changes and the end of input, which cover none.
+    fn of_token(kind: &TokenKind) -> Option<Self> {
+        Some(match kind {
+            TokenKind::Keyword(_) => SyntaxKind::Keyword,
+            TokenKind::Identifier(_) => SyntaxKind::Identifier,
+            TokenKind::Operator(_) => SyntaxKind::Operator,
+            TokenKind::CompoundOperator(_) => SyntaxKind::CompoundOperator,
+            TokenKind::Punctuation(_) => SyntaxKind::Punctuation,
+            TokenKind::Whitespace(_) => SyntaxKind::Whitespace,
+            TokenKind::Comment(_) => SyntaxKind::Comment,
+            TokenKind::Newline => SyntaxKind::Newline,
+            TokenKind::LeftParenthese => SyntaxKind::LeftParenthese,
+            TokenKind::RightParenthese => SyntaxKind::RightParenthese,
+            TokenKind::Backslash => SyntaxKind::Backslash,
+            TokenKind::Arrow => SyntaxKind::Arrow,
+            TokenKind::DoubleDot => SyntaxKind::DoubleDot,
+            TokenKind::Dot => SyntaxKind::Dot,
+            TokenKind::LeftBrace => SyntaxKind::LeftBrace,
+            TokenKind::RightBrace => SyntaxKind::RightBrace,
+            TokenKind::LeftBracket => SyntaxKind::LeftBracket,
+            TokenKind::RightBracket => SyntaxKind::RightBracket,
+            TokenKind::Pipe => SyntaxKind::Pipe,
+            TokenKind::Integer(_) => SyntaxKind::Integer,
+            TokenKind::Indent | TokenKind::Dendent | TokenKind::End => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GreenToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    pub text_len: usize,
+    pub children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+/// Assembles a green tree bottom up: tokens are added in order, and a node takes in
+/// everything added since the checkpoint it started at, which lets the parser decide what
+/// a node is once it has parsed it, or wrap a node it already built into a new one.
+#[derive(Debug, Default)]
+pub struct GreenNodeBuilder {
+    children: Vec<GreenElement>,
+}
+
+impl GreenNodeBuilder {
+    pub fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn token(&mut self, kind: SyntaxKind, text: &str) {
+        self.children.push(GreenElement::Token(Rc::new(GreenToken {
+            kind,
+            text: text.to_owned(),
+        })));
+    }
+
+    /// Makes everything added since `checkpoint` the children of a new node.
+    pub fn finish_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        let children: Vec<GreenElement> = self.children.drain(checkpoint..).collect();
+        let text_len = children.iter().map(GreenElement::text_len).sum();
+        self.children.push(GreenElement::Node(Rc::new(GreenNode {
+            kind,
+            text_len,
+            children,
+        })));
+    }
+
+    /// Drops everything added since `checkpoint`.
+    pub fn rewind(&mut self, checkpoint: usize) {
+        self.children.truncate(checkpoint);
+    }
+
+    /// The root, of the given kind, holding everything added.
+    pub fn finish(mut self, kind: SyntaxKind) -> Rc<GreenNode> {
+        self.finish_node_at(0, kind);
+        match self.children.pop() {
+            Some(GreenElement::Node(root)) => root,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The green tree of a source text as the parser builds it. The parser only sees some of the
+/// tokens: the whitespace, the comments and the line breaks [`logical_lines`] joins are filled
+/// in from the source in front of the next token it eats or the next node it starts, so that
+/// nodes begin and end with tokens of their own.
+#[derive(Debug)]
+pub(crate) struct SyntaxBuilder {
+    source: String,
+    /// Every token of the source covering some text, in order.
+    tokens: Vec<(SyntaxKind, SourceSpan)>,
+    /// How far into the source the tokens added so far reach.
+    text_end: usize,
+    green: GreenNodeBuilder,
+}
+
+/// Where a [`SyntaxBuilder`] stood, to go back to when the parser backtracks.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SyntaxCheckpoint {
+    children: usize,
+    text_end: usize,
+}
+
+/// What the parser added to the tree from some [`SyntaxCheckpoint`] on, to add again when it
+/// replays a memoized parse.
+#[derive(Debug, Clone)]
+pub(crate) struct Emitted {
+    elements: Vec<GreenElement>,
+    text_end: usize,
+}
+
+impl SyntaxBuilder {
+    pub(crate) fn new(source: &str) -> Self {
+        let tokens = TokenStream::new(source)
+            .filter(|token| token.span.0 < token.span.1)
+            .filter_map(|token| Some((SyntaxKind::of_token(&token.kind)?, token.span)))
+            .collect();
+        Self {
+            source: source.to_owned(),
+            tokens,
+            text_end: 0,
+            green: GreenNodeBuilder::default(),
+        }
+    }
+
+    pub(crate) fn checkpoint(&self) -> SyntaxCheckpoint {
+        SyntaxCheckpoint {
+            children: self.green.checkpoint(),
+            text_end: self.text_end,
+        }
+    }
+
+    pub(crate) fn rewind(&mut self, checkpoint: SyntaxCheckpoint) {
+        self.green.rewind(checkpoint.children);
+        self.text_end = checkpoint.text_end;
+    }
+
+    /// Adds the tokens from where the last one added ends up to `offset`.
+    pub(crate) fn fill(&mut self, offset: usize) {
+        let first = self.tokens.partition_point(|(_, span)| span.0 < self.text_end);
+        for &(kind, span) in &self.tokens[first..] {
+            if span.1 > offset {
+                break;
+            }
+            self.green.token(kind, &self.source[span.0..span.1]);
+            self.text_end = span.1;
+        }
+    }
+
+    /// Adds `token` along with what comes before it, unless it covers no text as do the
+    /// indentation changes.
+    pub(crate) fn token(&mut self, token: &Token) {
+        if SyntaxKind::of_token(&token.kind).is_some() && token.span.0 < token.span.1 {
+            self.fill(token.span.1);
+        }
+    }
+
+    /// Starts a node at `offset`, what comes before going to the node around it.
+    pub(crate) fn start_node(&mut self, offset: usize) -> usize {
+        self.fill(offset);
+        self.green.checkpoint()
+    }
+
+    pub(crate) fn finish_node(&mut self, start: usize, kind: SyntaxKind) {
+        self.green.finish_node_at(start, kind);
+    }
+
+    pub(crate) fn emitted_since(&self, checkpoint: SyntaxCheckpoint) -> Emitted {
+        Emitted {
+            elements: self.green.children[checkpoint.children..].to_vec(),
+            text_end: self.text_end,
+        }
+    }
+
+    pub(crate) fn replay(&mut self, emitted: &Emitted) {
+        self.green.children.extend(emitted.elements.iter().cloned());
+        self.text_end = emitted.text_end;
+    }
+
+    /// The root of the tree, taking in what is left of the source.
+    pub(crate) fn finish(mut self) -> Rc<GreenNode> {
+        self.fill(self.source.len());
+        self.green.finish(SyntaxKind::SourceFile)
+    }
+}
+
+#[derive(Debug)]
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Debug, Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn text_range(&self) -> SourceSpan {
+        (self.0.offset, self.0.offset + self.0.green.text_len)
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.to_owned()
+    }
+
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.to_owned()), SyntaxNode::parent)
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut out = vec![];
+        for child in &self.0.green.children {
+            out.push(match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.to_owned(),
+                    parent: Some(self.to_owned()),
+                    offset,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.to_owned(),
+                    parent: self.to_owned(),
+                    offset,
+                }),
+            });
+            offset += child.text_len();
+        }
+        out
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+    }
+
+    /// This node and every node below it, in preorder.
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut out = vec![self.to_owned()];
+        for child in self.children() {
+            out.extend(child.descendants());
+        }
+        out
+    }
+
+    /// Every token below this node, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut out = vec![];
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => out.extend(node.tokens()),
+                SyntaxElement::Token(token) => out.push(token),
+            }
+        }
+        out
+    }
+
+    pub fn text(&self) -> String {
+        self.tokens().iter().map(SyntaxToken::text).collect()
+    }
+
+    /// The innermost token covering `offset`.
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        self.tokens().into_iter().find(|token| {
+            let (start, end) = token.text_range();
+            start <= offset && offset < end
+        })
+    }
+
+    /// Renders the tree one element per line, e.g. `Name@4..5`.
+    pub fn debug_dump(&self) -> String {
+        let mut out = String::new();
+        self.dump_into(&mut out, 0);
+        out
+    }
+
+    fn dump_into(&self, out: &mut String, depth: usize) {
+        let (start, end) = self.text_range();
+        out.push_str(&format!("{}{:?}@{start}..{end}\n", "  ".repeat(depth), self.kind()));
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => node.dump_into(out, depth + 1),
+                SyntaxElement::Token(token) => {
+                    let (start, end) = token.text_range();
+                    out.push_str(&format!(
+                        "{}{:?}@{start}..{end} {:?}\n",
+                        "  ".repeat(depth + 1),
+                        token.kind(),
+                        token.text()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn text_range(&self) -> SourceSpan {
+        (self.offset, self.offset + self.green.text.len())
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.to_owned()
+    }
+}
+
+/// A typed view of a [`SyntaxNode`] of some kinds.
+pub trait AstNode: Sized {
+    fn can_cast(kind: SyntaxKind) -> bool;
+    fn cast(node: SyntaxNode) -> Option<Self>;
+    fn syntax(&self) -> &SyntaxNode;
+
+    fn kind(&self) -> SyntaxKind {
+        self.syntax().kind()
+    }
+
+    /// The tokens directly below the node, trivia left out.
+    fn tokens(&self) -> Vec<SyntaxToken> {
+        self.syntax()
+            .children_with_tokens()
+            .into_iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Token(token) if !token.kind().is_trivia() => Some(token),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The first identifier directly below the node, its name if it has one.
+    fn name(&self) -> Option<SyntaxToken> {
+        self.tokens()
+            .into_iter()
+            .find(|token| token.kind() == SyntaxKind::Identifier)
+    }
+}
+
+macro_rules! ast_node {
+    ($view:ident, $($kind:ident)|+) => {
+        #[derive(Debug, Clone)]
+        pub struct $view(SyntaxNode);
+
+        impl AstNode for $view {
+            fn can_cast(kind: SyntaxKind) -> bool {
+                matches!(kind, $(SyntaxKind::$kind)|+)
+            }
+
+            fn cast(node: SyntaxNode) -> Option<Self> {
+                Self::can_cast(node.kind()).then_some(Self(node))
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+ast_node!(BlockNode, Block);
+ast_node!(
+    StatementNode,
+    NameDeclaration
+        | Destructure
+        | While
+        | If
+        | For
+        | Break
+        | Continue
+        | FunctionDef
+        | StructDef
+        | EnumDef
+        | Match
+        | Return
+        | Import
+        | FromImport
+        | Public
+        | ExpressionStmt
+);
+ast_node!(
+    ExpressionNode,
+    IntegerLiteral
+        | Binop
+        | Name
+        | FunctionApplication
+        | Grouping
+        | Lambda
+        | Range
+        | Record
+        | FieldAccess
+        | Array
+        | Index
+        | Tuple
+);
+ast_node!(
+    PatternNode,
+    WildcardPattern
+        | NamePattern
+        | IntegerPattern
+        | ConstructorPattern
+        | TuplePattern
+        | GroupingPattern
+);
+ast_node!(
+    TypeNode,
+    NamedType | GenericType | FunctionType | TupleType | ArrayType | GroupingType
+);
+
+impl BlockNode {
+    pub fn statements(&self) -> impl Iterator<Item = StatementNode> {
+        self.0.children().filter_map(StatementNode::cast)
+    }
+}
+
+impl StatementNode {
+    pub fn blocks(&self) -> impl Iterator<Item = BlockNode> {
+        self.0.children().filter_map(BlockNode::cast)
+    }
+
+    pub fn expressions(&self) -> impl Iterator<Item = ExpressionNode> {
+        self.0.children().filter_map(ExpressionNode::cast)
+    }
+
+    pub fn patterns(&self) -> impl Iterator<Item = PatternNode> {
+        self.0.children().filter_map(PatternNode::cast)
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = TypeNode> {
+        self.0.children().filter_map(TypeNode::cast)
+    }
+
+    /// The item a `pub` statement exports.
+    pub fn item(&self) -> Option<StatementNode> {
+        self.0.children().find_map(StatementNode::cast)
+    }
+}
+
+impl ExpressionNode {
+    pub fn operands(&self) -> impl Iterator<Item = ExpressionNode> {
+        self.0.children().filter_map(ExpressionNode::cast)
+    }
+
+    /// The operator of a binary operation.
+    pub fn operator(&self) -> Option<SyntaxToken> {
+        self.tokens()
+            .into_iter()
+            .find(|token| token.kind() == SyntaxKind::Operator)
+    }
+}
+
+impl PatternNode {
+    /// The patterns of the arguments of a constructor or the elements of a tuple.
+    pub fn patterns(&self) -> impl Iterator<Item = PatternNode> {
+        self.0.children().filter_map(PatternNode::cast)
+    }
+}
+
+impl TypeNode {
+    /// The type arguments, elements or parameters, the return type of a function coming last.
+    pub fn types(&self) -> impl Iterator<Item = TypeNode> {
+        self.0.children().filter_map(TypeNode::cast)
+    }
+}
+
+/// The root of a lossless tree, along with the AST parsed from the same tokens.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    syntax: SyntaxNode,
+    ast: StatementBlock,
+}
+
+impl SourceFile {
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.syntax
+    }
+
+    pub fn ast(&self) -> &StatementBlock {
+        &self.ast
+    }
+
+    pub fn block(&self) -> Option<BlockNode> {
+        self.syntax.children().find_map(BlockNode::cast)
+    }
+
+    pub fn comments(&self) -> Vec<SyntaxToken> {
+        self.syntax
+            .tokens()
+            .into_iter()
+            .filter(|token| token.kind() == SyntaxKind::Comment)
+            .collect()
+    }
+}
+
+/// Parses `source` into a lossless tree, built by the parser along with the AST.
+pub fn parse_lossless(source: &str) -> Result<SourceFile, SyntaxError> {
+    let tokens = indented_tokens(logical_lines(TokenStream::new(source)).into_iter())?;
+    let tokens: Vec<Token> = omitted_spaces(tokens).into_iter().collect();
+    let mut parser = Parser::with_syntax(tokens, source);
+    let ast = parser.parse_module()?;
+    let green = parser.finish_syntax().expect("the parser builds a syntax tree");
+
+    Ok(SourceFile {
+        syntax: SyntaxNode::new_root(green),
+        ast,
+    })
+}
@@ -0,0 +1,179 @@
+//! Information about nodes kept beside the tree instead of in it, keyed by their [`NodeId`].
+//! The parser counts ids up from zero, so the tables are plain vectors indexed by them.
+
+use std::ops::Index;
+
+use r#macro::SourceObject;
+
+use crate::{
+    visit::{walk_block, walk_expression, walk_pattern, walk_stmt, walk_type_expression, Visit},
+    Expression, NodeId, Pattern, Statement, StatementBlock, TypeExpression,
+};
+
+/// Maps node ids to values of `T`, for passes that learn something about the nodes, such as
+/// their types or the symbols they resolve to.
+#[derive(Debug, Clone)]
+pub struct SideTable<T> {
+    slots: Vec<Option<T>>,
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        Self { slots: vec![] }
+    }
+}
+
+impl<T> SideTable<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value` for `id`, returning what was stored for it before.
+    pub fn insert(&mut self, id: NodeId, value: T) -> Option<T> {
+        let index = id.0 as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+        self.slots[index].replace(value)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.slots.get(id.0 as usize)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.slots.get_mut(id.0 as usize)?.as_mut()
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.slots.get_mut(id.0 as usize)?.take()
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// The stored values by ascending id.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| Some((NodeId(index as u32), slot.as_ref()?)))
+    }
+}
+
+impl<T> Index<NodeId> for SideTable<T> {
+    type Output = T;
+
+    fn index(&self, id: NodeId) -> &T {
+        self.get(id).unwrap_or_else(|| panic!("no entry for node {}", id.0))
+    }
+}
+
+/// A node of the tree, as found by its id.
+#[derive(Debug, Clone, Copy, SourceObject)]
+pub enum NodeRef<'a> {
+    Block(&'a StatementBlock),
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+    TypeExpression(&'a TypeExpression),
+    Pattern(&'a Pattern),
+}
+
+impl NodeRef<'_> {
+    pub fn id(&self) -> NodeId {
+        match self {
+            NodeRef::Block(block) => block.id(),
+            NodeRef::Statement(stmt) => stmt.id(),
+            NodeRef::Expression(expression) => expression.id(),
+            NodeRef::TypeExpression(ty) => ty.id(),
+            NodeRef::Pattern(pattern) => pattern.id(),
+        }
+    }
+}
+
+/// Every node of a tree by its id, along with the node it is a child of.
+#[derive(Debug, Default)]
+pub struct NodeIndex<'a> {
+    nodes: SideTable<NodeRef<'a>>,
+    parents: SideTable<NodeId>,
+}
+
+impl<'a> NodeIndex<'a> {
+    pub fn build(block: &'a StatementBlock) -> Self {
+        let mut builder = IndexBuilder {
+            index: NodeIndex::default(),
+            path: vec![],
+        };
+        builder.visit_block(block);
+        builder.index
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<NodeRef<'a>> {
+        self.nodes.get(id).copied()
+    }
+
+    /// The innermost node containing `id`, `None` for the root and for unknown ids.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents.get(id).copied()
+    }
+
+    /// The nodes containing `id`, innermost first.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), |&id| self.parent(id))
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = NodeRef<'a>> + '_ {
+        self.nodes.iter().map(|(_, &node)| node)
+    }
+}
+
+struct IndexBuilder<'a> {
+    index: NodeIndex<'a>,
+    /// The nodes being walked, outermost first.
+    path: Vec<NodeId>,
+}
+
+impl<'a> IndexBuilder<'a> {
+    fn enter(&mut self, node: NodeRef<'a>) {
+        let id = node.id();
+        let previous = self.index.nodes.insert(id, node);
+        debug_assert!(previous.is_none(), "node {} appears twice in the tree", id.0);
+        if let Some(&parent) = self.path.last() {
+            self.index.parents.insert(id, parent);
+        }
+        self.path.push(id);
+    }
+}
+
+impl<'a> Visit<'a> for IndexBuilder<'a> {
+    fn visit_block(&mut self, block: &'a StatementBlock) {
+        self.enter(NodeRef::Block(block));
+        walk_block(self, block);
+        self.path.pop();
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a Statement) {
+        self.enter(NodeRef::Statement(stmt));
+        walk_stmt(self, stmt);
+        self.path.pop();
+    }
+
+    fn visit_expression(&mut self, expression: &'a Expression) {
+        self.enter(NodeRef::Expression(expression));
+        walk_expression(self, expression);
+        self.path.pop();
+    }
+
+    fn visit_type_expression(&mut self, ty: &'a TypeExpression) {
+        self.enter(NodeRef::TypeExpression(ty));
+        walk_type_expression(self, ty);
+        self.path.pop();
+    }
+
+    fn visit_pattern(&mut self, pattern: &'a Pattern) {
+        self.enter(NodeRef::Pattern(pattern));
+        walk_pattern(self, pattern);
+        self.path.pop();
+    }
+}
@@ -0,0 +1,7 @@
+use lex::{indented_tokens, logical_lines, omitted_spaces, TokenStream};
+use parse::{Parser, StatementBlock, SyntaxError};
+
+pub fn parse(source: &str) -> Result<StatementBlock, SyntaxError> {
+    let tokens = indented_tokens(logical_lines(TokenStream::new(source)).into_iter())?;
+    Ok(Parser::new(omitted_spaces(tokens)).parse_module()?)
+}
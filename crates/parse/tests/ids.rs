@@ -0,0 +1,101 @@
+mod common;
+
+use std::collections::HashSet;
+
+use parse::{
+    visit::{walk_block, walk_expression, walk_pattern, walk_stmt, walk_type_expression, Visit},
+    Expression, NodeId, Pattern, Statement, StatementBlock, TypeExpression,
+};
+
+/// Every id met in the tree, in the order they were met.
+#[derive(Default)]
+struct Ids(Vec<NodeId>);
+
+impl<'ast> Visit<'ast> for Ids {
+    fn visit_block(&mut self, block: &'ast StatementBlock) {
+        self.0.push(block.id());
+        walk_block(self, block)
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Statement) {
+        self.0.push(stmt.id());
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expression(&mut self, expression: &'ast Expression) {
+        self.0.push(expression.id());
+        walk_expression(self, expression)
+    }
+
+    fn visit_type_expression(&mut self, ty: &'ast TypeExpression) {
+        self.0.push(ty.id());
+        walk_type_expression(self, ty)
+    }
+
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        self.0.push(pattern.id());
+        walk_pattern(self, pattern)
+    }
+}
+
+fn ids(source: &str) -> Vec<NodeId> {
+    let mut ids = Ids::default();
+    ids.visit_block(&common::parse(source).unwrap());
+    ids.0
+}
+
+fn assert_unique(source: &str) {
+    let ids = ids(source);
+    let mut seen = HashSet::new();
+    for id in &ids {
+        assert!(seen.insert(id), "node {} appears twice in:\n{source}", id.0);
+    }
+}
+
+#[test]
+fn expressions_and_statements() {
+    assert_unique("let x = 1\nx + 2 * x\nx; x\n");
+}
+
+#[test]
+fn backtracked_applications_and_groupings() {
+    // the parser tries applications, groupings and postfix chains more than once
+    assert_unique("let r = add xs[0] (xs[1]) + (add 1) 2\nf(g (h 1), [a, b][0].v)\n");
+}
+
+#[test]
+fn types_and_patterns() {
+    assert_unique(
+        "struct Box: get: (Int) -> Int, v: [Int]\n\
+         enum Shape: Circle(Int) | Rect(Int, Int) | Empty\n\
+         fn area(s: Shape, r: Range<Int>) -> (Int, Bool):\n\
+         \x20   match s:\n\
+         \x20       Circle(r):\n\
+         \x20           return (r, true)\n\
+         \x20       Rect(w, _):\n\
+         \x20           return (w, false)\n\
+         let (a, (b, c)): (Int, (Int, Int)) = (1, (2, 3))\n",
+    );
+}
+
+#[test]
+fn statement_expressions_are_nodes_of_their_own() {
+    let block = common::parse("f 1\n").unwrap();
+    match &block.stmts[0].stmt {
+        parse::Stmt::Expression(expr) => assert_ne!(expr.id(), block.stmts[0].id()),
+        stmt => panic!("expected an expression statement, found {stmt:?}"),
+    }
+}
+
+#[test]
+fn patterns_and_types_are_indexed() {
+    let block = common::parse("let (a, b): (Int, Int) = (1, 2)\n").unwrap();
+    let index = parse::tables::NodeIndex::build(&block);
+    let patterns = index
+        .nodes()
+        .filter(|node| matches!(node, parse::tables::NodeRef::Pattern(_)));
+    let types = index
+        .nodes()
+        .filter(|node| matches!(node, parse::tables::NodeRef::TypeExpression(_)));
+    assert_eq!((patterns.count(), types.count()), (3, 3));
+}
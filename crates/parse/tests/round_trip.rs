@@ -0,0 +1,232 @@
+mod common;
+
+use lex::{Operator, SourceObject};
+use parse::{
+    dump,
+    syntax::{self, AstNode, ExpressionNode, PatternNode, SyntaxKind, TypeNode},
+    tables::{NodeIndex, NodeRef},
+    Expr, Printer, Stmt, SyntaxError,
+};
+
+/// Parses `source` and checks everything the other representations of the tree promise:
+/// the JSON dump reads back, the printed source parses back to the same tree, every node lies
+/// within its parent and the lossless syntax tree gives back the source text, with a node for
+/// every expression, pattern and type.
+fn assert_round_trips(source: &str) {
+    let block = common::parse(source).unwrap();
+
+    let reread = dump::ast_from_json(&dump::to_json(&block)).unwrap();
+    assert_eq!(format!("{reread:?}"), format!("{block:?}"), "the JSON dump must read back");
+
+    let printed = block.to_string();
+    let reparsed = common::parse(&printed).unwrap();
+    assert_eq!(reparsed, block, "the printed source must parse back:\n{printed}");
+
+    let index = NodeIndex::build(&block);
+    for node in index.nodes() {
+        if let Some(parent) = index.parent(node.id()) {
+            let (inner, outer) = (node.source_span(), index.get(parent).unwrap().source_span());
+            assert!(outer.0 <= inner.0 && inner.1 <= outer.1, "a node must lie within its parent");
+        }
+    }
+
+    let file = syntax::parse_lossless(source).unwrap();
+    assert_eq!(file.syntax().text(), source, "the syntax tree must be lossless");
+    assert_eq!(file.ast(), &block, "the syntax tree must be built along with the same AST");
+
+    let nodes = file.syntax().descendants();
+    let ranges = |can_cast: fn(SyntaxKind) -> bool| {
+        nodes
+            .iter()
+            .filter(|node| can_cast(node.kind()))
+            .map(|node| node.text_range())
+            .collect::<Vec<_>>()
+    };
+    let (expressions, patterns, types) = (
+        ranges(ExpressionNode::can_cast),
+        ranges(PatternNode::can_cast),
+        ranges(TypeNode::can_cast),
+    );
+    for node in index.nodes() {
+        let (ranges, span) = match node {
+            NodeRef::Expression(expression) => (&expressions, expression.source_span()),
+            NodeRef::Pattern(pattern) => (&patterns, pattern.source_span()),
+            NodeRef::TypeExpression(ty) => (&types, ty.source_span()),
+            _ => continue,
+        };
+        assert!(ranges.contains(&span), "{node:?} must have a syntax node covering {span:?}");
+    }
+}
+
+/// Prints `source` back with only the parentheses the printer finds necessary.
+fn reprinted(source: &str) -> String {
+    let mut printer = Printer::default();
+    printer.keep_groupings = false;
+    printer.block(&common::parse(source).unwrap());
+    printer.finish()
+}
+
+#[test]
+fn sample() {
+    assert_round_trips(include_str!("../../../sample.wb"));
+}
+
+#[test]
+fn every_statement() {
+    assert_round_trips(
+        "import foo.bar\n\
+         from foo import baz, qux\n\
+         pub let x: Int = 1 + 2 * 3\n\
+         struct P: x: Int, y: Int\n\
+         enum Shape: Circle(Int) | Rect(Int, Int) | Empty\n\
+         pub fn area(s: Shape) -> Int:\n\
+         \x20   match s:\n\
+         \x20       Circle(r):\n\
+         \x20           return r * r * 3\n\
+         \x20       Rect(w, h):\n\
+         \x20           return w * h\n\
+         \x20       _:\n\
+         \x20           return\n\
+         let (a, (b, c)) = (1, (2, 3))\n\
+         for i in 0..10:\n\
+         \x20   if i > 5:\n\
+         \x20       break\n\
+         \x20   elif i < 2:\n\
+         \x20       continue\n\
+         \x20   else:\n\
+         \x20       x = i\n\
+         while x > 0:\n\
+         \x20   x = x - 1\n",
+    );
+}
+
+#[test]
+fn every_expression() {
+    assert_round_trips(
+        "let f = \\a b -> a + b\n\
+         let r = P { x: 1, y: 2 }\n\
+         let g = map (\\a -> a) [1, 2, 3]\n\
+         let h = f() 1 + add(3, 4) * 2\n\
+         let i = [r, r][0].x\n\
+         let t = ((), (1,), (1, 2))\n\
+         let u = util.double(2)\n",
+    );
+}
+
+#[test]
+fn precedence_and_parens() {
+    let cases = [
+        ("a + b * c\n", "a + b * c\n"),
+        ("a + (b * c)\n", "a + b * c\n"),
+        ("(a + b) * c\n", "(a + b) * c\n"),
+        ("((a + b)) * c\n", "(a + b) * c\n"),
+        ("(a - b) - c\n", "a - b - c\n"),
+        ("a - (b - c)\n", "a - (b - c)\n"),
+        // applying an application is another tree than applying to both arguments
+        ("(f x) y\n", "(f x) y\n"),
+        ("f (x y)\n", "f (x y)\n"),
+        ("(\\x -> x) 1\n", "(\\x -> x) 1\n"),
+        ("let y = (1 + 2) * 3\n", "let y = (1 + 2) * 3\n"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(reprinted(source), expected, "reprinting {source:?}");
+        assert_round_trips(source);
+    }
+}
+
+#[test]
+fn comparison_chains() {
+    let cases = [
+        ("a < b < c\n", "a < b < c\n"),
+        ("a < b > c < d\n", "a < b > c < d\n"),
+        // parenthesized comparisons compare a `Bool`, they do not chain
+        ("(a < b) < c\n", "(a < b) < c\n"),
+        ("a < (b < c)\n", "a < (b < c)\n"),
+        ("a + 1 < b * 2 < c\n", "a + 1 < b * 2 < c\n"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(reprinted(source), expected, "reprinting {source:?}");
+        assert_round_trips(source);
+    }
+}
+
+#[test]
+fn assignments() {
+    let cases = [
+        ("f = \\y -> y + 1\n", "f = \\y -> y + 1\n"),
+        ("xs = 0..10\n", "xs = 0..10\n"),
+        ("x = y = 1 + 2\n", "x = y = 1 + 2\n"),
+        ("(x = y) = 1\n", "(x = y) = 1\n"),
+        ("x.y = f 1\n", "x.y = f 1\n"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(reprinted(source), expected, "reprinting {source:?}");
+        assert_round_trips(source);
+    }
+
+    let block = common::parse("f = \\y -> y + 1\n").unwrap();
+    let Stmt::Expression(assignment) = &block.stmts[0].stmt else {
+        panic!("an assignment is an expression statement");
+    };
+    let Expr::Binop(binop) = &assignment.expr else {
+        panic!("an assignment is a binary operation");
+    };
+    assert_eq!(binop.op, Operator::Equals);
+    assert!(matches!(binop.rhs.expr, Expr::Lambda(_)));
+}
+
+#[test]
+fn semicolons() {
+    let block = common::parse("let x = 1; let y = 2\nf x;\ng y; h\n").unwrap();
+    assert_eq!(block.stmts.len(), 5);
+    assert_eq!(block.to_string(), "let x = 1\nlet y = 2\nf x\ng y\nh\n");
+    assert_round_trips("let x = 1; let y = 2\nf x;\ng y; h\n");
+}
+
+#[test]
+fn empty_modules() {
+    for source in ["", "\n", "\n\n\n", "# only a comment\n"] {
+        let block = common::parse(source).unwrap();
+        assert!(block.stmts.is_empty(), "{source:?} has no statements");
+        assert_eq!(block.to_string(), "");
+        assert_round_trips(source);
+    }
+}
+
+#[test]
+fn trailing_garbage() {
+    // the fault is at the first token nothing could make sense of
+    let cases = [
+        ("let x = 1 )\n", (1, 11)),
+        ("let x = 1\n)\n", (2, 1)),
+        ("f x ]\n", (1, 5)),
+        ("let x = 1 2 +\n", (1, 14)),
+        ("let x = [1, 2\n", (2, 1)),
+        ("let x: = 1\n", (1, 8)),
+        ("if a:\n    let x = 1\n    let y = 2 )\n", (3, 15)),
+    ];
+    for (source, (lineno, col)) in cases {
+        let loc = common::parse(source).expect_err(source).loc();
+        assert_eq!((loc.lineno, loc.col), (lineno, col), "fault in {source:?}");
+    }
+}
+
+#[test]
+fn misindentation() {
+    let cases = [
+        ("    let x = 1\n", (1, 1), "unexpected indentation"),
+        ("if a:\n        let x = 1\n", (2, 1), "indentation deeper than one level"),
+        (
+            "if a:\n    let x = 1\n            let y = 2\n",
+            (3, 1),
+            "indentation deeper than one level",
+        ),
+    ];
+    for (source, (lineno, col), message) in cases {
+        let err = common::parse(source).expect_err(source);
+        assert!(matches!(err, SyntaxError::Indentation(_)), "{err:?}");
+        assert_eq!((err.loc().lineno, err.loc().col), (lineno, col), "error in {source:?}");
+        assert_eq!(err.to_string(), message);
+        assert!(syntax::parse_lossless(source).is_err());
+    }
+}
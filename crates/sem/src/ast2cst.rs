@@ -1,9 +1,12 @@
-use crate::{cst, ty::Type};
+use lex::{Operator, SourceObject, SourceSpan};
+
+use crate::{captures::free_variables, cst, ty::Type};
 use parse as ast;
 
 impl From<ast::Statement> for cst::Statement {
     fn from(value: ast::Statement) -> Self {
         Self {
+            span: value.source_span(),
             stmt: value.stmt.into(),
         }
     }
@@ -12,18 +15,107 @@ impl From<ast::Statement> for cst::Statement {
 impl From<ast::Stmt> for cst::Stmt {
     fn from(value: ast::Stmt) -> Self {
         match value {
-            ast::Stmt::NameDeclaration { name, value } => Self::NameDeclaration {
+            ast::Stmt::NameDeclaration { name, ty, value } => Self::NameDeclaration {
                 name,
+                ty: ty.map(Into::into),
+                value: value.into(),
+            },
+            ast::Stmt::Destructure { pattern, ty, value } => Self::Destructure {
+                pattern: pattern.into(),
+                ty: ty.map(Into::into),
                 value: value.into(),
             },
             ast::Stmt::WhileStmt { pred, body } => Self::While {
                 pred: pred.into(),
                 body: body.into(),
             },
-            ast::Stmt::Expression(expr) => Self::Expression(cst::Expression {
-                expr: expr.into(),
+            ast::Stmt::IfStmt {
+                pred,
+                body,
+                elifs,
+                else_body,
+            } => {
+                // `elif` chains are lowered into nested `if`s living in the `else` branch
+                let else_body = elifs.into_iter().rev().fold(
+                    else_body.map(Into::into),
+                    |else_body: Option<cst::StatementBlock>, (pred, body)| {
+                        // from the `elif` condition to the end of the chain
+                        let end = else_body.as_ref().map_or(body.source_span(), |else_body| {
+                            else_body.span
+                        });
+                        let span = join(pred.source_span(), end);
+                        Some(cst::StatementBlock {
+                            span,
+                            stmts: vec![cst::Statement {
+                                span,
+                                stmt: Self::If {
+                                    pred: pred.into(),
+                                    body: body.into(),
+                                    else_body,
+                                },
+                            }],
+                        })
+                    },
+                );
+
+                Self::If {
+                    pred: pred.into(),
+                    body: body.into(),
+                    else_body,
+                }
+            }
+            ast::Stmt::ForStmt {
+                name,
+                iterable,
+                body,
+            } => Self::For {
+                name,
+                iterable: iterable.into(),
+                body: body.into(),
+            },
+            ast::Stmt::Break => Self::Break,
+            ast::Stmt::Continue => Self::Continue,
+            ast::Stmt::FunctionDef {
+                name,
+                params,
+                ret,
+                body,
+            } => Self::FunctionDef {
+                name,
+                params: params
+                    .into_iter()
+                    .map(|(param, ty)| (param, ty.map(Into::into)))
+                    .collect(),
+                ret: ret.map(Into::into),
+                body: body.into(),
                 ty: Type::default(),
-            }),
+            },
+            ast::Stmt::StructDef { name, fields } => Self::StructDef {
+                name,
+                fields: fields
+                    .into_iter()
+                    .map(|(field, ty)| (field, ty.into()))
+                    .collect(),
+            },
+            ast::Stmt::EnumDef { name, variants } => Self::EnumDef {
+                name,
+                variants: variants
+                    .into_iter()
+                    .map(|(variant, fields)| (variant, fields.into_iter().map(Into::into).collect()))
+                    .collect(),
+            },
+            ast::Stmt::MatchStmt { scrutinee, arms } => Self::Match {
+                scrutinee: scrutinee.into(),
+                arms: arms
+                    .into_iter()
+                    .map(|(pattern, body)| (pattern.into(), body.into()))
+                    .collect(),
+            },
+            ast::Stmt::Return { value } => Self::Return(value.map(Into::into)),
+            ast::Stmt::Import { path } => Self::Import { path },
+            ast::Stmt::FromImport { path, names } => Self::FromImport { path, names },
+            ast::Stmt::Public(item) => Self::Public(Box::new((*item).into())),
+            ast::Stmt::Expression(expr) => Self::Expression(expr.into()),
         }
     }
 }
@@ -31,6 +123,7 @@ impl From<ast::Stmt> for cst::Stmt {
 impl From<ast::StatementBlock> for cst::StatementBlock {
     fn from(value: ast::StatementBlock) -> Self {
         Self {
+            span: value.source_span(),
             stmts: value.stmts.into_iter().map(Into::into).collect(),
         }
     }
@@ -39,23 +132,275 @@ impl From<ast::StatementBlock> for cst::StatementBlock {
 impl From<ast::Expression> for cst::Expression {
     fn from(value: ast::Expression) -> Self {
         Self {
+            span: value.source_span(),
             ty: Type::default(),
             expr: value.expr.into(),
         }
     }
 }
 
+fn lower(expression: ast::Expression) -> Box<cst::Expression> {
+    Box::new(expression.into())
+}
+
+fn untyped(expr: cst::Expr, span: SourceSpan) -> Box<cst::Expression> {
+    Box::new(cst::Expression {
+        span,
+        ty: Type::default(),
+        expr,
+    })
+}
+
+/// The span from the start of `first` to the end of `last`.
+fn join(first: SourceSpan, last: SourceSpan) -> SourceSpan {
+    (first.0, last.1)
+}
+
+/// Lowers the comparison chain `a < b < c` into `a < b and b < c`. The operands standing in
+/// two comparisons are bound to temporaries so that they are evaluated once, and so is the
+/// first one, so that it is still evaluated before them. Names and integers are left as is.
+fn lower_chain(binop: ast::BinopExpr) -> cst::Expr {
+    // `a < b < c` parses as `(a < b) < c`, the first operand being the deepest
+    let mut ops = vec![binop.op];
+    let mut operands = vec![binop.rhs];
+    let mut first = binop.lhs;
+    loop {
+        let lhs = match &first.expr {
+            ast::Expr::Binop(lhs) if ast::is_comparison(&lhs.op) => lhs.to_owned(),
+            _ => break,
+        };
+        ops.push(lhs.op);
+        operands.push(lhs.rhs);
+        first = lhs.lhs;
+    }
+    operands.push(first);
+    operands.reverse();
+    ops.reverse();
+
+    // each operand but the last as it is referred to, along with the binding it needs
+    let last = lower(*operands.pop().unwrap());
+    let (bindings, references): (Vec<_>, Vec<_>) = operands
+        .into_iter()
+        .enumerate()
+        .map(|(i, operand)| {
+            let operand = lower(*operand);
+            match operand.expr {
+                cst::Expr::Name(_) | cst::Expr::Integer(_) => (None, operand),
+                _ => {
+                    let name = format!("%chain{i}");
+                    let reference = untyped(cst::Expr::Name(name.to_owned()), operand.span);
+                    (Some((name, operand)), reference)
+                }
+            }
+        })
+        .unzip();
+
+    // built from the last comparison outwards, each operand bound right before the comparison
+    // that first needs it, and only evaluated if all the ones before that hold
+    let mut ops = ops.into_iter().enumerate().rev();
+    let (i, op) = ops.next().unwrap();
+    let mut span = join(references[i].span, last.span);
+    let mut chain = cst::Expr::Binop {
+        op,
+        lhs: references[i].to_owned(),
+        rhs: last,
+    };
+    let mut bindings = bindings.into_iter().rev();
+    for (i, op) in ops {
+        let comparison = cst::Expr::Binop {
+            op,
+            lhs: references[i].to_owned(),
+            rhs: references[i + 1].to_owned(),
+        };
+        let comparison_span = join(references[i].span, references[i + 1].span);
+        chain = cst::Expr::And {
+            lhs: untyped(comparison, comparison_span),
+            rhs: untyped(chain, span),
+        };
+        span = join(comparison_span, span);
+        chain = bind(bindings.next().unwrap(), chain, span);
+    }
+    bind(bindings.next().unwrap(), chain, span)
+}
+
+fn bind(
+    binding: Option<(String, Box<cst::Expression>)>,
+    body: cst::Expr,
+    span: SourceSpan,
+) -> cst::Expr {
+    match binding {
+        Some((name, value)) => cst::Expr::Let {
+            name,
+            value,
+            body: untyped(body, span),
+        },
+        None => body,
+    }
+}
+
 impl From<ast::Expr> for cst::Expr {
     fn from(value: ast::Expr) -> Self {
         match value {
             ast::Expr::IntegerLiteral(n) => Self::Integer(n),
-            ast::Expr::Binop(binop) => Self::Binop {
-                op: binop.op,
-                lhs: Box::new((*binop.lhs).into()),
-                rhs: Box::new((*binop.rhs).into()),
+            ast::Expr::Binop(binop) => match binop.op {
+                Operator::And => Self::And {
+                    lhs: lower(*binop.lhs),
+                    rhs: lower(*binop.rhs),
+                },
+                Operator::Or => Self::Or {
+                    lhs: lower(*binop.lhs),
+                    rhs: lower(*binop.rhs),
+                },
+                op if ast::is_comparison(&op)
+                    && matches!(&binop.lhs.expr, ast::Expr::Binop(lhs) if ast::is_comparison(&lhs.op)) =>
+                {
+                    lower_chain(binop)
+                }
+                op => Self::Binop {
+                    op,
+                    lhs: lower(*binop.lhs),
+                    rhs: lower(*binop.rhs),
+                },
             },
             ast::Expr::Name(name) => Self::Name(name),
-            _ => todo!(),
+            ast::Expr::FunctionApplication(fa) => Self::FunctionApplication {
+                func: lower(*fa.func),
+                args: fa.args.into_iter().map(Into::into).collect(),
+            },
+            // parentheses only matter for parsing, the tree shape already encodes them
+            ast::Expr::Grouping { expr } => expr.expr.into(),
+            ast::Expr::Range(range) => Self::Range {
+                start: lower(*range.start),
+                end: lower(*range.end),
+            },
+            ast::Expr::Record(record) => Self::Record {
+                name: record.name,
+                fields: record
+                    .fields
+                    .into_iter()
+                    .map(|(field, value)| (field, value.into()))
+                    .collect(),
+            },
+            ast::Expr::FieldAccess(access) => Self::FieldAccess {
+                base: lower(*access.base),
+                field: access.field,
+            },
+            ast::Expr::Array(array) => {
+                Self::Array(array.elements.into_iter().map(Into::into).collect())
+            }
+            ast::Expr::Index(index) => {
+                let base = lower(*index.base);
+                let index = *index.index;
+                let span = index.source_span();
+                match index.expr {
+                    ast::Expr::Range(range) => Self::Slice {
+                        base,
+                        start: lower(*range.start),
+                        end: lower(*range.end),
+                    },
+                    expr => Self::Index {
+                        base,
+                        index: Box::new(cst::Expression {
+                            span,
+                            ty: Type::default(),
+                            expr: expr.into(),
+                        }),
+                    },
+                }
+            }
+            ast::Expr::Tuple(tuple) => {
+                Self::Tuple(tuple.elements.into_iter().map(Into::into).collect())
+            }
+            ast::Expr::Lambda(lambda) => {
+                let body: cst::Expression = (*lambda.body).into();
+                let captures = free_variables(&body)
+                    .into_iter()
+                    .filter(|name| !lambda.params.contains(name))
+                    .collect();
+
+                Self::Lambda {
+                    params: lambda.params,
+                    body: Box::new(body),
+                    captures,
+                }
+            }
         }
     }
 }
+
+impl From<ast::Pattern> for cst::Pattern {
+    fn from(value: ast::Pattern) -> Self {
+        match value.pat {
+            ast::Pat::Wildcard => Self::Wildcard,
+            // telling nullary constructors from bindings is up to the solver
+            ast::Pat::Name(name) => Self::Binding(name),
+            ast::Pat::Integer(n) => Self::Integer(n),
+            ast::Pat::Constructor { name, args } => Self::Constructor {
+                name,
+                args: args.into_iter().map(Into::into).collect(),
+            },
+            ast::Pat::Tuple(elements) => {
+                Self::Tuple(elements.into_iter().map(Into::into).collect())
+            }
+        }
+    }
+}
+
+impl From<ast::TypeExpression> for Type {
+    fn from(value: ast::TypeExpression) -> Self {
+        match value.ty {
+            ast::TypeExpr::Named(name) => match name.as_str() {
+                "Int" => Type::Integer,
+                "Bool" => Type::Bool,
+                // whether the struct exists, or if it is an enum, is up to the solver
+                _ => Type::Struct(name),
+            },
+            ast::TypeExpr::Generic { name, mut args } => match name.as_str() {
+                "Range" if args.len() == 1 => Type::Range(Box::new(args.remove(0).into())),
+                // there are no user defined generics, the solver reports the unknown type
+                _ => Type::Struct(name),
+            },
+            ast::TypeExpr::Function { params, ret } => Type::Function {
+                params: params.into_iter().map(Into::into).collect(),
+                ret: Box::new((*ret).into()),
+            },
+            ast::TypeExpr::Tuple(elements) => {
+                Type::Tuple(elements.into_iter().map(Into::into).collect())
+            }
+            ast::TypeExpr::Array(elem) => Type::Array(Box::new((*elem).into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cst::{Expr, Stmt},
+        modules::parse_source,
+    };
+
+    #[test]
+    fn spans() {
+        let block = parse_source("let b = 1 < a + 1 < 3\n").unwrap();
+        assert_eq!(block.span, (0, 21));
+        let Stmt::NameDeclaration { value, .. } = &block.stmts[0].stmt else {
+            panic!("not a declaration: {:?}", block.stmts[0]);
+        };
+        // the operand bound to a temporary keeps its span, so do the comparisons using it
+        let Expr::Let { value: operand, body, .. } = &value.expr else {
+            panic!("not lowered: {value:?}");
+        };
+        assert_eq!((value.span, operand.span, body.span), ((8, 21), (12, 17), (8, 21)));
+        let Expr::And { lhs, rhs } = &body.expr else {
+            panic!("not a conjunction: {body:?}");
+        };
+        assert_eq!((lhs.span, rhs.span), ((8, 17), (12, 21)));
+
+        // a lowered `elif` spans from its condition to the end of the chain
+        let block = parse_source("if a:\n    a\nelif b:\n    b\nelse:\n    c\n").unwrap();
+        let Stmt::If { else_body: Some(else_body), .. } = &block.stmts[0].stmt else {
+            panic!("not an if: {:?}", block.stmts[0]);
+        };
+        assert_eq!((else_body.span, else_body.stmts[0].span), ((17, 37), (17, 37)));
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    cst::{Expr, Expression},
+    visit::{walk_expr, Visit},
+};
+
+#[derive(Default)]
+struct FreeVariables(BTreeSet<String>);
+
+impl Visit<'_> for FreeVariables {
+    fn visit_expr(&mut self, expr: &Expression) {
+        match &expr.expr {
+            Expr::Name(name) => {
+                self.0.insert(name.to_owned());
+            }
+            // the captures of a nested lambda are already its free variables
+            Expr::Lambda { captures, .. } => self.0.extend(captures.iter().cloned()),
+            Expr::Let { name, value, body } => {
+                self.visit_expr(value);
+                let mut free = free_variables(body);
+                free.remove(name);
+                self.0.extend(free);
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+}
+
+/// Names an expression refers to without binding them itself, i.e. what a closure has to capture.
+pub fn free_variables(expr: &Expression) -> BTreeSet<String> {
+    let mut free = FreeVariables::default();
+    free.visit_expr(expr);
+    free.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cst::{Expr, Stmt},
+        modules::parse_source,
+    };
+
+    /// The captures of the lambda `source` declares first.
+    fn captures_of(source: &str) -> Vec<String> {
+        let block = parse_source(source).unwrap();
+        match &block.stmts[0].stmt {
+            Stmt::NameDeclaration { value, .. } => match &value.expr {
+                Expr::Lambda { captures, .. } => captures.to_owned(),
+                expr => panic!("not a lambda: {expr:?}"),
+            },
+            stmt => panic!("not a declaration: {stmt:?}"),
+        }
+    }
+
+    #[test]
+    fn captures() {
+        assert_eq!(captures_of("let f = \\x -> x + y\n"), ["y"]);
+        assert_eq!(captures_of("let f = \\x y -> x + y\n"), Vec::<String>::new());
+        // a nested lambda captures for the one around it what that one does not bind
+        assert_eq!(captures_of("let f = \\x -> \\y -> x + y + z\n"), ["z"]);
+        // the names comparison chains are lowered with are bound within the lambda
+        assert_eq!(captures_of("let f = \\x -> a < f x < b\n"), ["a", "b", "f"]);
+    }
+}
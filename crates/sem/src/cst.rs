@@ -1,8 +1,16 @@
+use std::fmt::Display;
+
 use crate::ty::{Type, Typed};
-use lex::Operator;
+use lex::{Operator, SourceSpan};
+use r#macro::Visitable;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+/// The root of the tree, which brings the [`crate::visit`], [`crate::visit_mut`] and
+/// [`crate::fold`] visitors with a method for each of the nodes.
+#[derive(Debug, Clone, Serialize, Visitable)]
+#[visit(block, nodes(stmt = "Statement", expr = "Expression", pattern = "Pattern", type = "Type"))]
 pub struct StatementBlock {
+    pub span: SourceSpan,
     pub stmts: Vec<Statement>,
 }
 
@@ -12,8 +20,10 @@ impl Typed for StatementBlock {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Visitable)]
+#[visit(stmt)]
 pub struct Statement {
+    pub span: SourceSpan,
     pub stmt: Stmt,
 }
 
@@ -23,25 +33,66 @@ impl Typed for Statement {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Visitable)]
 pub enum Stmt {
-    NameDeclaration { name: String, value: Expression },
+    NameDeclaration { name: String, ty: Option<Type>, value: Expression },
+    Destructure { pattern: Pattern, ty: Option<Type>, value: Expression },
     While { pred: Expression, body: StatementBlock },
+    If { pred: Expression, body: StatementBlock, else_body: Option<StatementBlock> },
+    For { name: String, iterable: Expression, body: StatementBlock },
+    Break,
+    Continue,
+    FunctionDef {
+        name: String,
+        params: Vec<(String, Option<Type>)>,
+        ret: Option<Type>,
+        body: StatementBlock,
+        ty: Type,
+    },
+    StructDef { name: String, fields: Vec<(String, Type)> },
+    EnumDef { name: String, variants: Vec<(String, Vec<Type>)> },
+    Match { scrutinee: Expression, arms: Vec<(Pattern, StatementBlock)> },
+    Return(Option<Expression>),
+    Import { path: Vec<String> },
+    FromImport { path: Vec<String>, names: Vec<String> },
+    Public(Box<Statement>),
     Expression(Expression),
 }
 
 impl Typed for Stmt {
     fn is_complete(&self) -> bool {
         match self {
-            Stmt::NameDeclaration { value, .. } => value.is_complete(),
+            Stmt::NameDeclaration { value, .. } | Stmt::Destructure { value, .. } => {
+                value.is_complete()
+            }
             Stmt::While { pred, body } => pred.is_complete() && body.is_complete(),
+            Stmt::If { pred, body, else_body } => {
+                pred.is_complete()
+                    && body.is_complete()
+                    && else_body.as_ref().is_none_or(Typed::is_complete)
+            }
+            Stmt::For { iterable, body, .. } => iterable.is_complete() && body.is_complete(),
+            Stmt::Break
+            | Stmt::Continue
+            | Stmt::StructDef { .. }
+            | Stmt::EnumDef { .. }
+            | Stmt::Import { .. }
+            | Stmt::FromImport { .. } => true,
+            Stmt::Public(item) => item.is_complete(),
+            Stmt::Match { scrutinee, arms } => {
+                scrutinee.is_complete() && arms.iter().all(|(_, body)| body.is_complete())
+            }
+            Stmt::FunctionDef { body, ty, .. } => ty.is_complete() && body.is_complete(),
+            Stmt::Return(value) => value.as_ref().is_none_or(Typed::is_complete),
             Stmt::Expression(expr) => expr.is_complete(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Visitable)]
+#[visit(expr)]
 pub struct Expression {
+    pub span: SourceSpan,
     pub ty: Type,
     pub expr: Expr,
 }
@@ -52,9 +103,54 @@ impl Typed for Expression {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Visitable)]
 pub enum Expr {
     Name(String),
     Binop{ op: Operator, lhs: Box<Expression>, rhs: Box<Expression> },
+    /// `rhs` is only evaluated when `lhs` holds.
+    And { lhs: Box<Expression>, rhs: Box<Expression> },
+    /// `rhs` is only evaluated when `lhs` does not hold.
+    Or { lhs: Box<Expression>, rhs: Box<Expression> },
+    /// `body` with `value` bound to `name`, which only lowering introduces: its names are
+    /// never identifiers, so they cannot clash with those in the source.
+    Let { name: String, value: Box<Expression>, body: Box<Expression> },
     Integer(String),
+    FunctionApplication { func: Box<Expression>, args: Vec<Expression> },
+    Lambda { params: Vec<String>, body: Box<Expression>, captures: Vec<String> },
+    Range { start: Box<Expression>, end: Box<Expression> },
+    Record { name: String, fields: Vec<(String, Expression)> },
+    FieldAccess { base: Box<Expression>, field: String },
+    Array(Vec<Expression>),
+    Index { base: Box<Expression>, index: Box<Expression> },
+    Slice { base: Box<Expression>, start: Box<Expression>, end: Box<Expression> },
+    Tuple(Vec<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Visitable)]
+#[visit(pattern)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    Integer(String),
+    Constructor { name: String, args: Vec<Pattern> },
+    Tuple(Vec<Pattern>),
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Binding(name) | Pattern::Integer(name) => write!(f, "{name}"),
+            Pattern::Constructor { name, args } if args.is_empty() => write!(f, "{name}"),
+            Pattern::Constructor { name, args } => {
+                let args: Vec<String> = args.iter().map(ToString::to_string).collect();
+                write!(f, "{name}({})", args.join(", "))
+            }
+            Pattern::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            Pattern::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(ToString::to_string).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+        }
+    }
 }
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::{cst::Pattern, ty::Type};
+
+/// Constructors of an enum along with the types of their fields.
+pub type Variants = Vec<(String, Vec<Type>)>;
+
+fn variants_of<'a>(enums: &'a BTreeMap<String, Variants>, ctor: &str) -> Option<&'a Variants> {
+    enums
+        .values()
+        .find(|variants| variants.iter().any(|(name, _)| name == ctor))
+}
+
+/// Keeps the rows whose first pattern can match `head`, replacing it with its sub-patterns.
+fn specialize(matrix: &[Vec<Pattern>], head: &Pattern, arity: usize) -> Vec<Vec<Pattern>> {
+    let mut out = vec![];
+    for row in matrix {
+        let mut specialized = match (&row[0], head) {
+            (Pattern::Constructor { name, args }, Pattern::Constructor { name: ctor, .. })
+                if name == ctor && args.len() == arity =>
+            {
+                args.to_owned()
+            }
+            (Pattern::Tuple(elements), Pattern::Tuple(_)) if elements.len() == arity => {
+                elements.to_owned()
+            }
+            (Pattern::Integer(n), Pattern::Integer(m)) if n == m => vec![],
+            (Pattern::Wildcard | Pattern::Binding(_), _) => vec![Pattern::Wildcard; arity],
+            _ => continue,
+        };
+        specialized.extend_from_slice(&row[1..]);
+        out.push(specialized);
+    }
+    out
+}
+
+/// Puts the first `arity` patterns of a witness back under the constructor or tuple `head`.
+fn rebuild(head: &Pattern, arity: usize, mut witness: Vec<Pattern>) -> Vec<Pattern> {
+    let rest = witness.split_off(arity);
+    let mut out = vec![match head {
+        Pattern::Tuple(_) => Pattern::Tuple(witness),
+        Pattern::Constructor { name, .. } => Pattern::Constructor {
+            name: name.to_owned(),
+            args: witness,
+        },
+        _ => unreachable!(),
+    }];
+    out.extend(rest);
+    out
+}
+
+/// Values matched by `vector` that no row of `matrix` matches, empty when `vector` is useless.
+///
+/// This is the usefulness check from "Warnings for pattern matching" by Maranget,
+/// extended to collect every witness instead of stopping at the first one.
+pub fn uncovered(
+    enums: &BTreeMap<String, Variants>,
+    matrix: &[Vec<Pattern>],
+    vector: &[Pattern],
+) -> Vec<Vec<Pattern>> {
+    let (head, rest) = match vector.split_first() {
+        Some(split) => split,
+        None if matrix.is_empty() => return vec![vec![]],
+        None => return vec![],
+    };
+
+    match head {
+        Pattern::Constructor { args, .. } => {
+            let mut vector = args.to_owned();
+            vector.extend_from_slice(rest);
+            uncovered(enums, &specialize(matrix, head, args.len()), &vector)
+                .into_iter()
+                .map(|witness| rebuild(head, args.len(), witness))
+                .collect()
+        }
+        Pattern::Tuple(elements) => {
+            let mut vector = elements.to_owned();
+            vector.extend_from_slice(rest);
+            uncovered(enums, &specialize(matrix, head, elements.len()), &vector)
+                .into_iter()
+                .map(|witness| rebuild(head, elements.len(), witness))
+                .collect()
+        }
+        Pattern::Integer(_) => uncovered(enums, &specialize(matrix, head, 0), rest)
+            .into_iter()
+            .map(|witness| [vec![head.to_owned()], witness].concat())
+            .collect(),
+        Pattern::Wildcard | Pattern::Binding(_) => {
+            // a tuple is its type's only constructor, so it never leaves anything out
+            let tuple = matrix.iter().find_map(|row| match &row[0] {
+                Pattern::Tuple(elements) => Some(elements.len()),
+                _ => None,
+            });
+            if let Some(arity) = tuple {
+                let head = Pattern::Tuple(vec![Pattern::Wildcard; arity]);
+                let mut vector = vec![Pattern::Wildcard; arity];
+                vector.extend_from_slice(rest);
+                return uncovered(enums, &specialize(matrix, &head, arity), &vector)
+                    .into_iter()
+                    .map(|witness| rebuild(&head, arity, witness))
+                    .collect();
+            }
+
+            let heads: Vec<&str> = matrix
+                .iter()
+                .filter_map(|row| match &row[0] {
+                    Pattern::Constructor { name, .. } => Some(name.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let variants = heads.first().and_then(|ctor| variants_of(enums, ctor));
+
+            let mut out = vec![];
+
+            // constructors that appear in the column are checked one by one so that
+            // every missing value below them is listed, not just the first one
+            for (ctor, fields) in variants.into_iter().flatten() {
+                if !heads.contains(&ctor.as_str()) {
+                    continue;
+                }
+
+                let head = Pattern::Constructor {
+                    name: ctor.to_owned(),
+                    args: vec![Pattern::Wildcard; fields.len()],
+                };
+                let mut vector = vec![Pattern::Wildcard; fields.len()];
+                vector.extend_from_slice(rest);
+
+                let specialized = specialize(matrix, &head, fields.len());
+                for witness in uncovered(enums, &specialized, &vector) {
+                    out.push(rebuild(&head, fields.len(), witness));
+                }
+            }
+
+            // whatever is not in the column can only be matched by the wildcard rows
+            let missing: Vec<Pattern> = match variants {
+                Some(variants) => variants
+                    .iter()
+                    .filter(|(ctor, _)| !heads.contains(&ctor.as_str()))
+                    .map(|(ctor, fields)| Pattern::Constructor {
+                        name: ctor.to_owned(),
+                        args: vec![Pattern::Wildcard; fields.len()],
+                    })
+                    .collect(),
+                None => vec![Pattern::Wildcard],
+            };
+
+            if !missing.is_empty() {
+                let default: Vec<Vec<Pattern>> = matrix
+                    .iter()
+                    .filter(|row| matches!(row[0], Pattern::Wildcard | Pattern::Binding(_)))
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+
+                for witness in uncovered(enums, &default, rest) {
+                    for head in &missing {
+                        out.push([vec![head.to_owned()], witness.to_owned()].concat());
+                    }
+                }
+            }
+
+            out
+        }
+    }
+}
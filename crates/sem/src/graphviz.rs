@@ -1,8 +1,9 @@
 use std::io::{self, Write};
 
 use crate::{
-    cst::{Expr, Expression, Statement, StatementBlock},
+    cst::{Expr, Expression, Pattern, Statement, StatementBlock, Stmt},
     ty::Type,
+    visit::Visit,
 };
 
 #[derive(Clone, Debug, Default)]
@@ -11,16 +12,26 @@ pub struct CstGraphvizVisualizer {
     pub type_nodes: Vec<(i32, String)>,
     pub edges: Vec<(i32, i32, String)>,
     pub counter: i32,
+    /// The node the next one drawn hangs from, along with the label of the edge to it.
+    parent: Option<(i32, String)>,
 }
 
 impl CstGraphvizVisualizer {
-    #[must_use]
+    /// Draws a node, below the one given to [`CstGraphvizVisualizer::below`] beforehand.
     pub fn new_node(&mut self, label: &str) -> i32 {
         self.counter += 1;
         self.nodes.push((self.counter, label.to_owned()));
+        if let Some((parent, edge)) = self.parent.take() {
+            self.new_edge(parent, self.counter, &edge);
+        }
         self.counter
     }
 
+    /// Draws a node without children below `parent`.
+    pub fn new_leaf(&mut self, parent: i32, edge: &str, label: &str) {
+        self.below(parent, edge).new_node(label);
+    }
+
     pub fn new_type_node(&mut self, typename: &str) -> i32 {
         self.counter += 1;
         self.type_nodes.push((self.counter, typename.to_owned()));
@@ -29,96 +40,262 @@ impl CstGraphvizVisualizer {
 
     #[must_use]
     pub fn get_type_node(&mut self, ty: &Type) -> i32 {
-        match ty {
-            Type::Variable(var) => self.new_type_node(format!("T{}", var).as_str()),
-            Type::Integer => self.new_type_node("Integer"),
-            Type::Bool => self.new_type_node("Bool"),
-        }
+        self.new_type_node(ty.to_string().as_str())
     }
 
     pub fn new_edge(&mut self, start: i32, end: i32, label: &str) {
         self.edges.push((start, end, label.to_owned()))
     }
 
+    /// Hangs the next node drawn from `parent`, along an edge labeled `edge`.
+    fn below(&mut self, parent: i32, edge: &str) -> &mut Self {
+        self.parent = Some((parent, edge.to_owned()));
+        self
+    }
+
+    /// Draws the type of `node` next to it.
+    fn typed(&mut self, node: i32, ty: &Type) {
+        let ty = self.get_type_node(ty);
+        self.new_edge(node, ty, "  : type");
+    }
+
     pub fn dump<W: Write>(&self, out: &mut W) -> io::Result<()> {
-        out.write("digraph {\n".as_bytes())?;
+        out.write_all("digraph {\n".as_bytes())?;
         for (vert, label) in &self.nodes {
-            out.write(format!("\t{vert} [label=\"{label}\"]\n").as_bytes())?;
+            out.write_all(format!("\t{vert} [label=\"{label}\"]\n").as_bytes())?;
         }
 
         for (vert, label) in &self.type_nodes {
-            out.write(format!("\t{vert} [label=\"{label}\" shape=none color=gray fontcolor=gray]\n").as_bytes())?;
+            out.write_all(format!("\t{vert} [label=\"{label}\" shape=none color=gray fontcolor=gray]\n").as_bytes())?;
         }
 
         for (start, end, label) in &self.edges {
             if self.type_nodes.iter().filter(|x| *end == x.0).count() == 1 {
-                out.write(format!("\t{start}->{end} [arrowhead=onormal color=gray fontcolor=gray]\n").as_bytes())?;
+                out.write_all(format!("\t{start}->{end} [arrowhead=onormal color=gray fontcolor=gray]\n").as_bytes())?;
             } else {
-                out.write(format!("\t{start}->{end} [label=\"{label}\"]\n").as_bytes())?;
+                out.write_all(format!("\t{start}->{end} [label=\"{label}\"]\n").as_bytes())?;
             }
         }
 
-        out.write("}\n".as_bytes())?;
+        out.write_all("}\n".as_bytes())?;
         Ok(())
     }
+}
 
-    pub fn visit_stmt_block(&mut self, block: &StatementBlock) -> i32 {
+impl<'ast> Visit<'ast> for CstGraphvizVisualizer {
+    fn visit_block(&mut self, block: &'ast StatementBlock) {
         let this = self.new_node("Block");
         for (i, stmt) in block.stmts.iter().enumerate() {
-            let stmt = self.visit_stmt(stmt);
-            self.new_edge(this, stmt, (i + 1).to_string().as_str());
+            self.below(this, &(i + 1).to_string()).visit_stmt(stmt);
         }
-
-        this
     }
 
-    pub fn visit_stmt(&mut self, stmt: &Statement) -> i32 {
+    fn visit_stmt(&mut self, stmt: &'ast Statement) {
         match &stmt.stmt {
-            crate::cst::Stmt::NameDeclaration { name, value } => {
+            Stmt::NameDeclaration { name, value, .. } => {
                 let this = self.new_node("Name Declaration");
-                let name = self.new_node(name.as_str());
-                let value = self.visit_expression(&value);
-
-                self.new_edge(this, name, "name");
-                self.new_edge(this, value, "value");
-
-                this
+                self.new_leaf(this, "name", name);
+                self.below(this, "value").visit_expr(value);
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                let this = self.new_node("Destructure");
+                self.below(this, "pattern").visit_pattern(pattern);
+                self.below(this, "value").visit_expr(value);
             }
-            crate::cst::Stmt::While { pred, body } => {
+            Stmt::While { pred, body } => {
                 let this = self.new_node("While");
-                let pred = self.visit_expression(pred);
-                let body = self.visit_stmt_block(body);
-
-                self.new_edge(this, pred, "pred");
-                self.new_edge(this, body, "body");
+                self.below(this, "pred").visit_expr(pred);
+                self.below(this, "body").visit_block(body);
+            }
+            Stmt::If { pred, body, else_body } => {
+                let this = self.new_node("If");
+                self.below(this, "pred").visit_expr(pred);
+                self.below(this, "then").visit_block(body);
+                if let Some(else_body) = else_body {
+                    self.below(this, "else").visit_block(else_body);
+                }
+            }
+            Stmt::For { name, iterable, body } => {
+                let this = self.new_node("For");
+                self.new_leaf(this, "name", name);
+                self.below(this, "in").visit_expr(iterable);
+                self.below(this, "body").visit_block(body);
+            }
+            Stmt::Break => {
+                self.new_node("Break");
+            }
+            Stmt::Continue => {
+                self.new_node("Continue");
+            }
+            Stmt::FunctionDef { name, params, body, ty, .. } => {
+                let this = self.new_node("Function Definition");
+                self.new_leaf(this, "name", name);
+                for (i, (param, _)) in params.iter().enumerate() {
+                    self.new_leaf(this, &format!("param {}", i + 1), param);
+                }
+                self.below(this, "body").visit_block(body);
+                self.typed(this, ty);
+            }
+            Stmt::StructDef { name, fields } => {
+                let this = self.new_node("Struct Definition");
+                self.new_leaf(this, "name", name);
+                for (field, ty) in fields {
+                    self.new_leaf(this, field, &ty.to_string());
+                }
+            }
+            Stmt::EnumDef { name, variants } => {
+                let this = self.new_node("Enum Definition");
+                self.new_leaf(this, "name", name);
+                for (variant, fields) in variants {
+                    let variant = self.below(this, "variant").new_node(variant);
+                    for (i, ty) in fields.iter().enumerate() {
+                        self.new_leaf(variant, &(i + 1).to_string(), &ty.to_string());
+                    }
+                }
+            }
+            Stmt::Match { scrutinee, arms } => {
+                let this = self.new_node("Match");
+                self.below(this, "scrutinee").visit_expr(scrutinee);
+                for (pattern, body) in arms {
+                    let arm = self.below(this, "").new_node("Arm");
+                    self.below(arm, "pattern").visit_pattern(pattern);
+                    self.below(arm, "body").visit_block(body);
+                }
+            }
+            Stmt::Return(value) => {
+                let this = self.new_node("Return");
+                if let Some(value) = value {
+                    self.below(this, "value").visit_expr(value);
+                }
+            }
+            Stmt::Import { path } => {
+                let this = self.new_node("Import");
+                self.new_leaf(this, "module", &path.join("."));
+            }
+            Stmt::FromImport { path, names } => {
+                let this = self.new_node("From Import");
+                self.new_leaf(this, "module", &path.join("."));
+                for name in names {
+                    self.new_leaf(this, "name", name);
+                }
+            }
+            Stmt::Public(item) => {
+                let this = self.new_node("Pub");
+                self.below(this, "item").visit_stmt(item);
+            }
+            // drawn in place of the statement, hanging from the same parent
+            Stmt::Expression(expr) => self.visit_expr(expr),
+        }
+    }
 
-                this
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        match pattern {
+            Pattern::Constructor { name, args } => {
+                let this = self.new_node(name);
+                for (i, arg) in args.iter().enumerate() {
+                    self.below(this, &(i + 1).to_string()).visit_pattern(arg);
+                }
+            }
+            Pattern::Tuple(elements) => {
+                let this = self.new_node("Tuple");
+                for (i, element) in elements.iter().enumerate() {
+                    self.below(this, &i.to_string()).visit_pattern(element);
+                }
+            }
+            _ => {
+                self.new_node(&pattern.to_string());
             }
-            crate::cst::Stmt::Expression(expr) => {
-                self.visit_expression(expr)
-            },
         }
     }
 
-    pub fn visit_expression(&mut self, expr: &Expression) -> i32 {
+    fn visit_expr(&mut self, expr: &'ast Expression) {
         let this = match &expr.expr {
-            Expr::Name(name) => self.new_node(name.as_str()),
+            Expr::Name(name) | Expr::Integer(name) => self.new_node(name),
             Expr::Binop { op, lhs, rhs } => {
                 let this = self.new_node(op.to_owned().into());
-                let lhs = self.visit_expression(&lhs);
-                let rhs = self.visit_expression(&rhs);
-
-                self.new_edge(this, lhs, "lhs");
-                self.new_edge(this, rhs, "rhs");
-
+                self.below(this, "lhs").visit_expr(lhs);
+                self.below(this, "rhs").visit_expr(rhs);
+                this
+            }
+            Expr::And { lhs, rhs } | Expr::Or { lhs, rhs } => {
+                let op = match &expr.expr {
+                    Expr::And { .. } => "and",
+                    _ => "or",
+                };
+                let this = self.new_node(op);
+                self.below(this, "lhs").visit_expr(lhs);
+                self.below(this, "rhs").visit_expr(rhs);
+                this
+            }
+            Expr::Let { name, value, body } => {
+                let this = self.new_node(&format!("let {name}"));
+                self.below(this, "value").visit_expr(value);
+                self.below(this, "body").visit_expr(body);
+                this
+            }
+            Expr::FunctionApplication { func, args } => {
+                let this = self.new_node("Function Application");
+                self.below(this, "func").visit_expr(func);
+                for (i, arg) in args.iter().enumerate() {
+                    self.below(this, &(i + 1).to_string()).visit_expr(arg);
+                }
+                this
+            }
+            Expr::Range { start, end } => {
+                let this = self.new_node("Range");
+                self.below(this, "start").visit_expr(start);
+                self.below(this, "end").visit_expr(end);
+                this
+            }
+            Expr::Record { name, fields } => {
+                let this = self.new_node(&format!("Record {name}"));
+                for (field, value) in fields {
+                    self.below(this, field).visit_expr(value);
+                }
+                this
+            }
+            Expr::FieldAccess { base, field } => {
+                let this = self.new_node(&format!(".{field}"));
+                self.below(this, "base").visit_expr(base);
+                this
+            }
+            Expr::Array(elements) | Expr::Tuple(elements) => {
+                let kind = match &expr.expr {
+                    Expr::Array(_) => "Array",
+                    _ => "Tuple",
+                };
+                let this = self.new_node(kind);
+                for (i, element) in elements.iter().enumerate() {
+                    self.below(this, &i.to_string()).visit_expr(element);
+                }
+                this
+            }
+            Expr::Index { base, index } => {
+                let this = self.new_node("Index");
+                self.below(this, "base").visit_expr(base);
+                self.below(this, "index").visit_expr(index);
+                this
+            }
+            Expr::Slice { base, start, end } => {
+                let this = self.new_node("Slice");
+                self.below(this, "base").visit_expr(base);
+                self.below(this, "start").visit_expr(start);
+                self.below(this, "end").visit_expr(end);
+                this
+            }
+            Expr::Lambda { params, body, captures } => {
+                let this = self.new_node("Lambda");
+                for (i, param) in params.iter().enumerate() {
+                    self.new_leaf(this, &format!("param {}", i + 1), param);
+                }
+                for capture in captures {
+                    self.new_leaf(this, "captures", capture);
+                }
+                self.below(this, "body").visit_expr(body);
                 this
             }
-            Expr::Integer(number) => self.new_node(number.as_str()),
         };
 
-        let ty = self.get_type_node(&expr.ty);
-        self.new_edge(this, ty, "  : type");
-
-        this
+        self.typed(this, &expr.ty);
     }
 }
@@ -1,46 +1,85 @@
 mod ast2cst;
+mod captures;
 mod cst;
+mod exhaustiveness;
 mod graphviz;
+mod modules;
 mod solver;
+mod substitution;
 mod ty;
 
-use cst::StatementBlock;
+use cst::{fold, visit, visit_mut};
 use graphviz::CstGraphvizVisualizer;
-use lex::*;
-use parse::Parser;
-use solver::TypeSolver;
-use std::fs;
-
-use crate::ty::Typed;
-
-fn main() {
-    let contents = fs::read_to_string("sample.wb").expect("Should have been able to read the file");
-    let tokens = try_tokenize(contents.as_str());
-    let indented_toks = indented_tokens(tokens);
-
-    let toks: Vec<Token> = omitted_spaces(indented_toks).into_iter().collect();
-    let mut parser = Parser::new(toks);
-    let block = parser.parse_stmt_block().unwrap();
-    let mut block: StatementBlock = block.into();
-
-    let mut solver = TypeSolver::default();
-    for stmt in &mut block.stmts {
-        solver.emplace_type_vars_in_stmt(stmt)
-    }
+use modules::ModuleResolver;
+use fold::Fold;
+use solver::{ResolvedTypes, TypeSolver};
+use visit::Visit;
+use std::{collections::BTreeMap, fs, process::ExitCode};
+
+fn main() -> ExitCode {
+    let resolver = ModuleResolver::new(".");
+    let entry = vec!["sample".to_owned()];
+    let modules = match resolver.resolve(&entry) {
+        Ok(modules) => modules,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let mut i = 0;
-    loop {
-        let mut visitor = CstGraphvizVisualizer::default();
-        visitor.visit_stmt_block(&block);
-        visitor
-            .dump(&mut fs::File::create(format!("out{i}.dot").as_str()).unwrap())
-            .unwrap();
+    // modules come after their imports, so every import is solved by the time it is needed
+    let mut scopes = BTreeMap::new();
+    let mut counter = 0;
+    let mut failed = false;
+    for mut module in modules {
+        let is_entry = module.name == entry.join(".");
+        let block = &mut module.block;
 
-        if block.is_complete() || i >= 10 {
-            break;
+        let mut solver = TypeSolver {
+            modules: scopes.to_owned(),
+            counter,
+            ..Default::default()
+        };
+        for stmt in &mut block.stmts {
+            solver.emplace_type_vars_in_stmt(stmt)
         }
 
-        solver.solve_stmt_block_recursive(&mut block);
-        i += 1;
+        if is_entry {
+            let mut visitor = CstGraphvizVisualizer::default();
+            visitor.visit_block(block);
+            visitor.dump(&mut fs::File::create("out0.dot").unwrap()).unwrap();
+        }
+
+        solver.solve(block);
+        let resolved = ResolvedTypes(&solver).fold_block(block.to_owned());
+
+        if is_entry {
+            let mut visitor = CstGraphvizVisualizer::default();
+            visitor.visit_block(&resolved);
+            visitor.dump(&mut fs::File::create("out1.dot").unwrap()).unwrap();
+
+            fs::write("out.json", parse::dump::to_json(&resolved)).unwrap();
+            fs::write("out.sexp", parse::dump::to_sexp(&resolved)).unwrap();
+        }
+
+        let errors = solver
+            .errors
+            .iter()
+            .cloned()
+            .chain(solver.check_stmt_block(&resolved))
+            .chain(solver.unresolved(&resolved));
+        for err in errors {
+            eprintln!("{}: error: {err}", module.name);
+            failed = true;
+        }
+
+        scopes.insert(module.name.to_owned(), solver.scope(block));
+        counter = solver.counter;
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
     }
 }
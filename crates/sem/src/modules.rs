@@ -0,0 +1,198 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    fs,
+    path::PathBuf,
+};
+
+use lex::{indented_tokens, omitted_spaces, logical_lines, Token, TokenStream};
+use parse::{Parser, SyntaxError};
+
+use crate::{
+    cst::{Pattern, Statement, StatementBlock, Stmt},
+    exhaustiveness::Variants,
+    ty::{Scheme, Type},
+    visit::{walk_pattern, walk_stmt, Visit},
+};
+
+/// A parsed source file, named by its dot-separated path, e.g. `foo.bar` for `foo/bar.wb`.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub block: StatementBlock,
+}
+
+/// The top-level names of a solved module, along with which of them are `pub`.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleScope {
+    pub symbol_table: BTreeMap<String, Scheme>,
+    pub structs: BTreeMap<String, Vec<(String, Type)>>,
+    pub enums: BTreeMap<String, Variants>,
+    pub public: BTreeSet<String>,
+}
+
+impl ModuleScope {
+    pub fn contains(&self, name: &str) -> bool {
+        self.symbol_table.contains_key(name)
+            || self.structs.contains_key(name)
+            || self.enums.contains_key(name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ModuleError {
+    NotFound { module: String, path: PathBuf },
+    Parse { module: String, error: SyntaxError },
+    Cycle(Vec<String>),
+}
+
+impl Display for ModuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModuleError::NotFound { module, path } => {
+                write!(f, "module `{module}` not found at {}", path.display())
+            }
+            ModuleError::Parse { module, error } => {
+                let loc = error.loc();
+                write!(f, "{error} in module `{module}` at {}:{}", loc.lineno, loc.col)
+            }
+            ModuleError::Cycle(cycle) => write!(f, "import cycle: {}", cycle.join(" -> ")),
+        }
+    }
+}
+
+/// Maps module paths to `.wb` files relative to the project root.
+#[derive(Debug, Clone)]
+pub struct ModuleResolver {
+    pub root: PathBuf,
+}
+
+impl ModuleResolver {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn file_of(&self, path: &[String]) -> PathBuf {
+        let mut file = self.root.to_owned();
+        file.extend(path);
+        file.set_extension("wb");
+        file
+    }
+
+    /// Loads `entry` and every module it transitively imports,
+    /// each module coming after all of the modules it imports.
+    pub fn resolve(&self, entry: &[String]) -> Result<Vec<Module>, ModuleError> {
+        let mut loaded = vec![];
+        self.load(entry, &mut vec![], &mut loaded)?;
+        Ok(loaded)
+    }
+
+    fn load(
+        &self,
+        path: &[String],
+        stack: &mut Vec<String>,
+        loaded: &mut Vec<Module>,
+    ) -> Result<(), ModuleError> {
+        let name = path.join(".");
+        if let Some(start) = stack.iter().position(|module| *module == name) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(name);
+            return Err(ModuleError::Cycle(cycle));
+        }
+        if loaded.iter().any(|module| module.name == name) {
+            return Ok(());
+        }
+
+        let file = self.file_of(path);
+        let contents = fs::read_to_string(&file).map_err(|_| ModuleError::NotFound {
+            module: name.to_owned(),
+            path: file,
+        })?;
+
+        let block = parse_source(&contents).map_err(|error| ModuleError::Parse {
+            module: name.to_owned(),
+            error,
+        })?;
+
+        stack.push(name.to_owned());
+        for import in imports_of(&block) {
+            self.load(import, stack, loaded)?;
+        }
+        stack.pop();
+
+        loaded.push(Module { name, block });
+        Ok(())
+    }
+}
+
+/// Parses the source of a module and lowers it.
+pub fn parse_source(source: &str) -> Result<StatementBlock, SyntaxError> {
+    let tokens = indented_tokens(logical_lines(TokenStream::new(source)).into_iter())?;
+    let tokens: Vec<Token> = omitted_spaces(tokens).into_iter().collect();
+    Ok(Parser::new(tokens).parse_module()?.into())
+}
+
+/// The modules `block` imports, including from within functions and other nested blocks.
+fn imports_of(block: &StatementBlock) -> Vec<&[String]> {
+    let mut imports = Imports::default();
+    imports.visit_block(block);
+    imports.0
+}
+
+#[derive(Default)]
+struct Imports<'ast>(Vec<&'ast [String]>);
+
+impl<'ast> Visit<'ast> for Imports<'ast> {
+    fn visit_stmt(&mut self, stmt: &'ast Statement) {
+        match &stmt.stmt {
+            Stmt::Import { path } | Stmt::FromImport { path, .. } => self.0.push(path),
+            _ => walk_stmt(self, stmt),
+        }
+    }
+}
+
+/// Names declared by the `pub` items at the top level of `block`.
+pub fn public_names(block: &StatementBlock) -> BTreeSet<String> {
+    let mut public = BTreeSet::new();
+    for stmt in &block.stmts {
+        let Stmt::Public(item) = &stmt.stmt else {
+            continue;
+        };
+        match &item.stmt {
+            Stmt::NameDeclaration { name, .. }
+            | Stmt::FunctionDef { name, .. }
+            | Stmt::StructDef { name, .. } => {
+                public.insert(name.to_owned());
+            }
+            // the constructors of a public enum are public as well
+            Stmt::EnumDef { name, variants } => {
+                public.insert(name.to_owned());
+                public.extend(variants.iter().map(|(variant, _)| variant.to_owned()));
+            }
+            Stmt::Destructure { pattern, .. } => public.extend(bindings_of(pattern)),
+            _ => {}
+        }
+    }
+    public
+}
+
+/// The names `pattern` binds.
+pub fn bindings_of(pattern: &Pattern) -> BTreeSet<String> {
+    let mut bindings = Bindings::default();
+    bindings.visit_pattern(pattern);
+    bindings.0
+}
+
+#[derive(Default)]
+struct Bindings(BTreeSet<String>);
+
+impl Visit<'_> for Bindings {
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Binding(name) => {
+                self.0.insert(name.to_owned());
+            }
+            _ => walk_pattern(self, pattern),
+        }
+    }
+}
@@ -1,120 +1,1713 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+use lex::Operator;
 
 use crate::{
-    cst::{Expr, Expression, Statement, StatementBlock},
-    ty::Type,
+    cst::{Expr, Expression, Pattern, Statement, StatementBlock, Stmt},
+    exhaustiveness::{uncovered, Variants},
+    fold::Fold,
+    modules::{bindings_of, public_names, ModuleScope},
+    substitution::Substitution,
+    ty::{Scheme, Type, TypeError, Typed},
+    visit::{walk_expr, walk_stmt, Visit},
+    visit_mut::{walk_stmt_mut, VisitMut},
 };
 
 #[derive(Debug, Clone, Default)]
 pub struct TypeSolver {
-    pub constraints: BTreeMap<u128, Type>,
-    pub symbol_table: BTreeMap<String, Type>,
+    pub substitution: Substitution,
+    pub symbol_table: BTreeMap<String, Scheme>,
+    pub structs: BTreeMap<String, Vec<(String, Type)>>,
+    pub enums: BTreeMap<String, Variants>,
     pub counter: u128,
+    pub return_types: Vec<Type>,
+    pub errors: Vec<TypeError>,
+    /// Scopes of the already solved modules, by dot-separated module path.
+    pub modules: BTreeMap<String, ModuleScope>,
+    /// Field accesses on values whose type was not known yet when they were solved.
+    pub pending_fields: Vec<PendingField>,
+    /// The variables of every [`Scheme`] made, which stand for any type rather than for one
+    /// that is yet to be inferred.
+    pub generalized: BTreeSet<u128>,
+    /// Names assigned to anywhere in the module, which are never generalized: an assignment
+    /// could store a value of another type than a use of the name reads.
+    pub assigned: BTreeSet<String>,
+}
+
+/// A field access waiting for the type of its base to be known.
+#[derive(Debug, Clone)]
+pub struct PendingField {
+    pub base: Type,
+    pub field: String,
+    pub ty: Type,
 }
 
 impl TypeSolver {
     pub fn make_var_type(&mut self) -> Type {
         self.counter += 1;
-        let typevar = Type::Variable(self.counter);
-        self.constraints.insert(self.counter, typevar.to_owned());
-        typevar
+        Type::Variable(self.counter)
+    }
+
+    pub fn make_function_type(&mut self, arity: usize) -> Type {
+        Type::Function {
+            params: (0..arity).map(|_| self.make_var_type()).collect(),
+            ret: Box::new(self.make_var_type()),
+        }
+    }
+
+    /// Generalizes the type of a declaration over the variables that appear nowhere else in
+    /// scope, so that each use of it can pick its own types for them.
+    fn generalize(&mut self, ty: &Type) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut vars = BTreeSet::new();
+        free_variables(&ty, &mut vars);
+
+        for scheme in self.symbol_table.values() {
+            if vars.is_empty() {
+                break;
+            }
+            let mut in_scope = BTreeSet::new();
+            free_variables(&self.resolve(&scheme.ty), &mut in_scope);
+            vars.retain(|var| !in_scope.contains(var) || scheme.vars.contains(var));
+        }
+
+        // a scheme cannot tell that a field is accessed on its values, which leaves the
+        // types of pending accesses to be found out from the uses of the declaration
+        for PendingField { base, ty: field_ty, .. } in &self.pending_fields {
+            let mut pending = BTreeSet::new();
+            free_variables(&self.resolve(base), &mut pending);
+            free_variables(&self.resolve(field_ty), &mut pending);
+            vars.retain(|var| !pending.contains(var));
+        }
+
+        self.generalized.extend(&vars);
+        Scheme { vars, ty }
+    }
+
+    /// The type of a use of a declaration, with fresh variables for those it was
+    /// generalized over.
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        if scheme.vars.is_empty() {
+            return scheme.ty.to_owned();
+        }
+        let fresh: BTreeMap<u128, Type> = scheme
+            .vars
+            .iter()
+            .map(|&var| (var, self.make_var_type()))
+            .collect();
+        substituted(&self.resolve(&scheme.ty), &fresh)
+    }
+
+    /// Substitutes every bound type variable in `ty` with what it is bound to, and every
+    /// unbound one with the representative of its class.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Variable(n) => match self.substitution.binding(*n) {
+                Some(bound) => self.resolve(bound),
+                None => Type::Variable(self.substitution.find(*n)),
+            },
+            Type::Function { params, ret } => Type::Function {
+                params: params.iter().map(|param| self.resolve(param)).collect(),
+                ret: Box::new(self.resolve(ret)),
+            },
+            Type::Range(elem) => Type::Range(Box::new(self.resolve(elem))),
+            Type::Array(elem) => Type::Array(Box::new(self.resolve(elem))),
+            Type::Tuple(elements) => {
+                Type::Tuple(elements.iter().map(|element| self.resolve(element)).collect())
+            }
+            Type::Integer | Type::Bool | Type::Struct(_) | Type::Enum(_) | Type::Module(_) => {
+                ty.to_owned()
+            }
+        }
+    }
+
+    /// Substitutes `ty` only where it is a variable, leaving the types inside of it as they
+    /// are, which is all [`TypeSolver::unify`] needs to look at before descending.
+    fn shallow_resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Variable(n) => match self.substitution.binding(*n) {
+                Some(bound) => bound.to_owned(),
+                None => Type::Variable(self.substitution.find(*n)),
+            },
+            _ => ty.to_owned(),
+        }
+    }
+
+    /// Whether the unbound variable `var` appears in `ty`, looking through bound variables.
+    /// Each class is looked into once, however many times the types share it, and ground
+    /// ones not at all.
+    fn occurs(&self, var: u128, ty: &Type, seen: &mut BTreeSet<u128>) -> bool {
+        match ty {
+            Type::Variable(n) => {
+                let root = self.substitution.find(*n);
+                if root == var {
+                    return true;
+                }
+                if self.substitution.is_ground(root) || !seen.insert(root) {
+                    return false;
+                }
+                match self.substitution.binding(root) {
+                    Some(bound) => self.occurs(var, bound, seen),
+                    None => false,
+                }
+            }
+            Type::Function { params, ret } => {
+                params.iter().any(|param| self.occurs(var, param, seen))
+                    || self.occurs(var, ret, seen)
+            }
+            Type::Range(elem) | Type::Array(elem) => self.occurs(var, elem, seen),
+            Type::Tuple(elements) => elements.iter().any(|element| self.occurs(var, element, seen)),
+            Type::Integer | Type::Bool | Type::Struct(_) | Type::Enum(_) | Type::Module(_) => false,
+        }
+    }
+
+    /// Binds the unbound variable `var` to `ty`, unless that would make an infinite type
+    /// like `T1 = [T1]`.
+    fn bind(&mut self, var: u128, ty: Type) {
+        if self.occurs(var, &ty, &mut BTreeSet::new()) {
+            self.errors.push(TypeError::InfiniteType {
+                var: Type::Variable(var),
+                ty: self.resolve(&ty),
+            });
+        } else {
+            self.substitution.bind(var, ty);
+        }
+    }
+
+    /// Makes two types equal, reporting where they cannot be. Only the variables met on the
+    /// way down get substituted, instead of both types as a whole at every level.
+    pub fn unify(&mut self, expected: &Type, found: &Type) {
+        // a class is equal to itself without looking at what it is bound to
+        if let (Type::Variable(l), Type::Variable(r)) = (expected, found) {
+            if self.substitution.find(*l) == self.substitution.find(*r) {
+                return;
+            }
+        }
+        let (expected, found) = (self.shallow_resolve(expected), self.shallow_resolve(found));
+        match (&expected, &found) {
+            (Type::Variable(l), Type::Variable(r)) => self.substitution.union(*l, *r),
+            (Type::Variable(n), _) => self.bind(*n, found),
+            (_, Type::Variable(n)) => self.bind(*n, expected),
+            (
+                Type::Function { params: lparams, ret: lret },
+                Type::Function { params: rparams, ret: rret },
+            ) => {
+                if lparams.len() != rparams.len() {
+                    self.errors.push(TypeError::ArityMismatch {
+                        expected: lparams.len(),
+                        found: rparams.len(),
+                    });
+                    return;
+                }
+                for (lparam, rparam) in lparams.iter().zip(rparams) {
+                    self.unify(lparam, rparam);
+                }
+                self.unify(lret, rret);
+            }
+            (Type::Range(lelem), Type::Range(relem)) | (Type::Array(lelem), Type::Array(relem)) => {
+                self.unify(lelem, relem)
+            }
+            (Type::Tuple(lelements), Type::Tuple(relements)) => {
+                if lelements.len() != relements.len() {
+                    self.errors.push(TypeError::TupleLengthMismatch {
+                        expected: lelements.len(),
+                        found: relements.len(),
+                    });
+                    return;
+                }
+                for (lelement, relement) in lelements.iter().zip(relements) {
+                    self.unify(lelement, relement);
+                }
+            }
+            _ if expected == found => {}
+            _ => self.errors.push(TypeError::Mismatch {
+                expected: self.resolve(&expected),
+                found: self.resolve(&found),
+            }),
+        }
     }
 
     pub fn emplace_type_vars_in_exprs(&mut self, expr: &mut Expression) {
         expr.ty = match &mut expr.expr {
-            Expr::Name(name) => {
-                let t = self.make_var_type();
-                self.symbol_table.insert(name.to_owned(), t.clone());
-                t
-            }
+            Expr::Name(_) => self.make_var_type(),
             Expr::Binop { lhs, rhs, .. } => {
                 self.emplace_type_vars_in_exprs(lhs);
                 self.emplace_type_vars_in_exprs(rhs);
                 self.make_var_type()
             }
+            Expr::And { lhs, rhs } | Expr::Or { lhs, rhs } => {
+                self.emplace_type_vars_in_exprs(lhs);
+                self.emplace_type_vars_in_exprs(rhs);
+                Type::Bool
+            }
+            Expr::Let { value, body, .. } => {
+                self.emplace_type_vars_in_exprs(value);
+                self.emplace_type_vars_in_exprs(body);
+                body.ty.to_owned()
+            }
             Expr::Integer(_) => Type::Integer,
+            Expr::FunctionApplication { func, args } => {
+                self.emplace_type_vars_in_exprs(func);
+                for arg in args {
+                    self.emplace_type_vars_in_exprs(arg);
+                }
+                self.make_var_type()
+            }
+            Expr::Lambda { params, body, .. } => {
+                self.emplace_type_vars_in_exprs(body);
+                self.make_function_type(params.len())
+            }
+            Expr::Range { start, end } => {
+                self.emplace_type_vars_in_exprs(start);
+                self.emplace_type_vars_in_exprs(end);
+                self.make_var_type()
+            }
+            Expr::Record { fields, .. } => {
+                for (_, value) in fields {
+                    self.emplace_type_vars_in_exprs(value);
+                }
+                self.make_var_type()
+            }
+            Expr::FieldAccess { base, .. } => {
+                self.emplace_type_vars_in_exprs(base);
+                self.make_var_type()
+            }
+            Expr::Array(elements) => {
+                for element in elements {
+                    self.emplace_type_vars_in_exprs(element);
+                }
+                Type::Array(Box::new(self.make_var_type()))
+            }
+            Expr::Index { base, index } => {
+                self.emplace_type_vars_in_exprs(base);
+                self.emplace_type_vars_in_exprs(index);
+                self.make_var_type()
+            }
+            Expr::Slice { base, start, end } => {
+                self.emplace_type_vars_in_exprs(base);
+                self.emplace_type_vars_in_exprs(start);
+                self.emplace_type_vars_in_exprs(end);
+                Type::Array(Box::new(self.make_var_type()))
+            }
+            Expr::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter_mut()
+                    .map(|element| {
+                        self.emplace_type_vars_in_exprs(element);
+                        element.ty.to_owned()
+                    })
+                    .collect(),
+            ),
         }
     }
 
     pub fn emplace_type_vars_in_stmt(&mut self, stmt: &mut Statement) {
-        for expr in self.shallow_expr_iterator_from_stmt(stmt) {
-            self.emplace_type_vars_in_exprs(expr);
-        }
+        FunctionTypes(self).visit_stmt_mut(stmt);
+        StatementExprs(self).visit_stmt_mut(stmt);
     }
 
     pub fn solve_stmt_block_recursive(&mut self, block: &mut StatementBlock) {
+        self.declare_items(block);
         for stmt in &mut block.stmts {
             self.solve_stmt_recursive(stmt);
         }
     }
 
+    /// Makes the structs, enums and functions of `block` known ahead of its statements, so
+    /// that they can be used before they are defined.
+    fn declare_items(&mut self, block: &mut StatementBlock) {
+        // every name first, the fields may refer to any of them
+        for stmt in &block.stmts {
+            let item = match &stmt.stmt {
+                Stmt::Public(item) => &item.stmt,
+                stmt => stmt,
+            };
+            match item {
+                Stmt::StructDef { name, fields } => {
+                    self.structs.insert(name.to_owned(), fields.to_owned());
+                }
+                Stmt::EnumDef { name, variants } => {
+                    self.enums.insert(name.to_owned(), variants.to_owned());
+                }
+                Stmt::FunctionDef { name, ty, .. } => {
+                    self.symbol_table.insert(name.to_owned(), ty.to_owned().into());
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in &mut block.stmts {
+            let item = match &mut stmt.stmt {
+                Stmt::Public(item) => &mut item.stmt,
+                stmt => stmt,
+            };
+            match item {
+                Stmt::StructDef { name, fields } => {
+                    for (_, ty) in fields.iter_mut() {
+                        *ty = self.declared_type(ty, Some(name));
+                    }
+                    self.structs.insert(name.to_owned(), fields.to_owned());
+                }
+                Stmt::EnumDef { name, variants } => {
+                    for (_, fields) in variants.iter_mut() {
+                        for ty in fields.iter_mut() {
+                            *ty = self.declared_type(ty, Some(name));
+                        }
+                    }
+                    self.enums.insert(name.to_owned(), variants.to_owned());
+
+                    let ty = Type::Enum(name.to_owned());
+                    for (variant, fields) in variants.iter() {
+                        let constructor = if fields.is_empty() {
+                            ty.to_owned()
+                        } else {
+                            Type::Function {
+                                params: fields.to_owned(),
+                                ret: Box::new(ty.to_owned()),
+                            }
+                        };
+                        self.symbol_table.insert(variant.to_owned(), constructor.into());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Solves a block whose names go out of scope at its end.
+    fn solve_scoped_block(&mut self, block: &mut StatementBlock) {
+        let outer_scope = self.symbol_table.clone();
+        self.solve_stmt_block_recursive(block);
+        self.symbol_table = outer_scope;
+    }
+
     pub fn solve_stmt_recursive(&mut self, stmt: &mut Statement) {
         match &mut stmt.stmt {
-            crate::cst::Stmt::NameDeclaration { name, value } => {
-                self.symbol_table
-                    .get_mut(name)
-                    .map(|ty| *ty = value.ty.to_owned());
+            crate::cst::Stmt::NameDeclaration { name, ty, value } => {
                 self.solve_expr_recursive(value);
+                self.solve_annotation(ty, value);
+                let scheme = if is_value(value) && !self.assigned.contains(name) {
+                    self.generalize(&value.ty)
+                } else {
+                    value.ty.to_owned().into()
+                };
+                self.symbol_table.insert(name.to_owned(), scheme);
+            }
+            crate::cst::Stmt::Destructure { pattern, ty, value } => {
+                self.solve_expr_recursive(value);
+                self.solve_annotation(ty, value);
+                self.solve_pattern(pattern, &value.ty);
+                // out of scope while generalizing, or they would hold on to their own variables
+                let bound: Vec<(String, Scheme)> = bindings_of(pattern)
+                    .into_iter()
+                    .filter_map(|name| Some((name.to_owned(), self.symbol_table.remove(&name)?)))
+                    .collect();
+                for (name, scheme) in bound {
+                    let scheme = if is_value(value) && !self.assigned.contains(&name) {
+                        self.generalize(&scheme.ty)
+                    } else {
+                        scheme
+                    };
+                    self.symbol_table.insert(name, scheme);
+                }
             }
             crate::cst::Stmt::While { pred, body } => {
                 self.solve_expr_recursive(pred);
+                self.unify(&Type::Bool, &pred.ty);
+                self.solve_scoped_block(body);
+            }
+            crate::cst::Stmt::If { pred, body, else_body } => {
+                self.solve_expr_recursive(pred);
+                self.unify(&Type::Bool, &pred.ty);
+                self.solve_scoped_block(body);
+                if let Some(else_body) = else_body {
+                    self.solve_scoped_block(else_body);
+                }
+            }
+            crate::cst::Stmt::For {
+                name,
+                iterable,
+                body,
+            } => {
+                self.solve_expr_recursive(iterable);
+                let elem = match self.shallow_resolve(&iterable.ty) {
+                    Type::Range(elem) => *elem,
+                    found => {
+                        let elem = self.make_var_type();
+                        self.unify(&Type::Range(Box::new(elem.to_owned())), &found);
+                        elem
+                    }
+                };
+
+                let outer_scope = self.symbol_table.clone();
+                self.symbol_table.insert(name.to_owned(), elem.into());
                 self.solve_stmt_block_recursive(body);
+                self.symbol_table = outer_scope;
+            }
+            crate::cst::Stmt::Break | crate::cst::Stmt::Continue => {}
+            // declared along with the other items of their block
+            crate::cst::Stmt::StructDef { .. } | crate::cst::Stmt::EnumDef { .. } => {}
+            crate::cst::Stmt::Match { scrutinee, arms } => {
+                self.solve_expr_recursive(scrutinee);
+                for (pattern, body) in arms.iter_mut() {
+                    let outer_scope = self.symbol_table.clone();
+                    self.solve_pattern(pattern, &scrutinee.ty);
+                    self.solve_stmt_block_recursive(body);
+                    self.symbol_table = outer_scope;
+                }
+            }
+            crate::cst::Stmt::FunctionDef {
+                name,
+                params,
+                ret: ret_annotation,
+                body,
+                ty,
+            } => {
+                // the function is visible in its own body so it can recurse, though only at
+                // the one type it is being solved for
+                self.symbol_table.insert(name.to_owned(), ty.to_owned().into());
+                let outer_scope = self.symbol_table.clone();
+
+                if let Type::Function {
+                    params: param_types,
+                    ret,
+                } = ty.to_owned()
+                {
+                    for ((param, annotation), param_type) in params.iter().zip(param_types) {
+                        if let Some(annotation) = annotation {
+                            let annotation = self.declared_type(annotation, None);
+                            self.unify(&annotation, &param_type);
+                        }
+                        self.symbol_table.insert(param.to_owned(), param_type.into());
+                    }
+                    if let Some(annotation) = ret_annotation {
+                        let annotation = self.declared_type(annotation, None);
+                        self.unify(&annotation, &ret);
+                    }
+                    self.return_types.push(*ret.to_owned());
+                    self.solve_stmt_block_recursive(body);
+                    self.return_types.pop();
+                    // falling off the end returns as a bare `return` does
+                    if !always_returns(body) {
+                        self.unify(&ret, &Type::Tuple(vec![]));
+                    }
+                }
+
+                self.symbol_table = outer_scope;
+                self.symbol_table.remove(name);
+                *ty = self.resolve(ty);
+                let scheme = if self.assigned.contains(name) {
+                    ty.to_owned().into()
+                } else {
+                    self.generalize(ty)
+                };
+                self.symbol_table.insert(name.to_owned(), scheme);
             }
+            crate::cst::Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => {
+                        self.solve_expr_recursive(value);
+                        value.ty.to_owned()
+                    }
+                    None => Type::Tuple(vec![]),
+                };
+                match self.return_types.last().cloned() {
+                    Some(ret) => self.unify(&ret, &value),
+                    None => self.errors.push(TypeError::ReturnOutsideFunction),
+                }
+            }
+            // `import foo.util` binds the module by the last segment of its path alone, as
+            // `util`, so its items are `util.item` rather than `foo.util.item`
+            crate::cst::Stmt::Import { path } => {
+                let module = path.join(".");
+                if self.modules.contains_key(&module) {
+                    let name = path.last().unwrap().to_owned();
+                    self.symbol_table.insert(name, Type::Module(module).into());
+                } else {
+                    self.errors.push(TypeError::UnknownModule(module));
+                }
+            }
+            crate::cst::Stmt::FromImport { path, names } => {
+                let module = path.join(".");
+                for name in names.iter() {
+                    self.import(&module, name);
+                }
+            }
+            crate::cst::Stmt::Public(item) => self.solve_stmt_recursive(item),
             crate::cst::Stmt::Expression(expr) => {
                 self.solve_expr_recursive(expr);
             }
         }
     }
 
+    /// Looks up a `pub` item of a solved module, reporting missing or private ones.
+    fn public_scope(&mut self, module: &str, name: &str) -> Option<ModuleScope> {
+        let Some(scope) = self.modules.get(module) else {
+            self.errors.push(TypeError::UnknownModule(module.to_owned()));
+            return None;
+        };
+
+        if scope.public.contains(name) {
+            return Some(scope.to_owned());
+        }
+
+        let (module, name) = (module.to_owned(), name.to_owned());
+        if scope.contains(&name) {
+            self.errors.push(TypeError::PrivateItem { module, name });
+        } else {
+            self.errors.push(TypeError::UnknownImport { module, name });
+        }
+        None
+    }
+
+    /// The error for `name` being unbound, telling when it starts the path of an imported
+    /// module, as `foo` does in `foo.util.item` after `import foo.util`.
+    fn unknown_name(&self, name: &str) -> TypeError {
+        let prefix = format!("{name}.");
+        let imported = self.symbol_table.iter().find_map(|(binding, scheme)| match &scheme.ty {
+            Type::Module(module) if module.starts_with(&prefix) => Some((binding, module)),
+            _ => None,
+        });
+        match imported {
+            Some((binding, module)) => TypeError::QualifiedModule {
+                name: name.to_owned(),
+                module: module.to_owned(),
+                binding: binding.to_owned(),
+            },
+            None => TypeError::UnknownName(name.to_owned()),
+        }
+    }
+
+    /// Brings a `pub` value, struct or enum of another module into scope.
+    fn import(&mut self, module: &str, name: &str) {
+        let Some(scope) = self.public_scope(module, name) else {
+            return;
+        };
+
+        if let Some(scheme) = scope.symbol_table.get(name) {
+            self.symbol_table.insert(name.to_owned(), scheme.to_owned());
+        }
+        if let Some(fields) = scope.structs.get(name) {
+            self.structs.insert(name.to_owned(), fields.to_owned());
+        }
+        if let Some(variants) = scope.enums.get(name) {
+            self.enums.insert(name.to_owned(), variants.to_owned());
+            for (variant, _) in variants {
+                if let Some(constructor) = scope.symbol_table.get(variant) {
+                    self.symbol_table.insert(variant.to_owned(), constructor.to_owned());
+                }
+            }
+        }
+    }
+
+    /// What the solved top-level `block` offers to the modules importing it.
+    pub fn scope(&self, block: &StatementBlock) -> ModuleScope {
+        ModuleScope {
+            symbol_table: self
+                .symbol_table
+                .iter()
+                .map(|(name, scheme)| {
+                    let ty = self.resolve(&scheme.ty);
+                    (name.to_owned(), Scheme { ty, ..scheme.to_owned() })
+                })
+                .collect(),
+            structs: self.structs.to_owned(),
+            enums: self.enums.to_owned(),
+            public: public_names(block),
+        }
+    }
+
+    /// The type of `field` of a value of type `base`, reporting missing fields.
+    fn field_type(&mut self, base: Type, field: &str) -> Option<Type> {
+        match base {
+            Type::Module(module) => self
+                .public_scope(&module, field)
+                .and_then(|scope| scope.symbol_table.get(field).cloned())
+                .map(|scheme| self.instantiate(&scheme)),
+            ty => {
+                let declared = match &ty {
+                    Type::Struct(name) => self.structs.get(name),
+                    _ => None,
+                };
+                match declared.and_then(|fields| fields.iter().find(|(f, _)| f == field)) {
+                    Some((_, field_ty)) => Some(field_ty.to_owned()),
+                    None => {
+                        self.errors.push(TypeError::UnknownField {
+                            ty: self.resolve(&ty),
+                            field: field.to_owned(),
+                        });
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Solves the field accesses put off until the type of their base was known, for as long
+    /// as that makes progress. Each round settles at least one access, so this terminates.
+    fn solve_pending_fields(&mut self) {
+        loop {
+            let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_fields)
+                .into_iter()
+                .partition(|pending| {
+                    !matches!(self.shallow_resolve(&pending.base), Type::Variable(_))
+                });
+            self.pending_fields = waiting;
+            if ready.is_empty() {
+                break;
+            }
+
+            for PendingField { base, field, ty } in ready {
+                let base = self.resolve(&base);
+                if let Some(field_ty) = self.field_type(base, &field) {
+                    self.unify(&ty, &field_ty);
+                }
+            }
+        }
+
+        for PendingField { field, .. } in std::mem::take(&mut self.pending_fields) {
+            self.errors.push(TypeError::UnresolvedField(field));
+        }
+    }
+
+    /// Solves a whole module: its statements in one pass, then whatever had to wait for them.
+    pub fn solve(&mut self, block: &mut StatementBlock) {
+        let mut assigned = Assigned::default();
+        assigned.visit_block(block);
+        self.assigned = assigned.0;
+
+        self.solve_stmt_block_recursive(block);
+        self.solve_pending_fields();
+    }
+
+    /// Reports the type variables the solved and resolved `block` is left with, by the first
+    /// declaration whose type holds them, and the rest all together.
+    pub fn unresolved(&self, block: &StatementBlock) -> Vec<TypeError> {
+        let mut collector = Unresolved::default();
+        collector.visit_block(block);
+
+        let mut errors = vec![];
+        // the variables of generic declarations are meant to stay open
+        let mut reported = self.generalized.to_owned();
+        for (name, ty) in collector.declarations {
+            let mut vars = BTreeSet::new();
+            free_variables(&ty, &mut vars);
+            if vars.iter().any(|var| !reported.contains(var)) {
+                reported.extend(vars);
+                errors.push(TypeError::AmbiguousType { name, ty });
+            }
+        }
+
+        let rest: Vec<Type> = collector
+            .variables
+            .difference(&reported)
+            .map(|&var| Type::Variable(var))
+            .collect();
+        if !rest.is_empty() {
+            errors.push(TypeError::UnresolvedVariables(rest));
+        }
+        errors
+    }
+
+    /// Tells apart struct and enum names used in a declaration and reports unknown ones.
+    fn declared_type(&mut self, ty: &Type, declaring: Option<&str>) -> Type {
+        match ty {
+            Type::Struct(name) if self.enums.contains_key(name) => Type::Enum(name.to_owned()),
+            Type::Struct(name)
+                if Some(name.as_str()) != declaring && !self.structs.contains_key(name) =>
+            {
+                self.errors.push(TypeError::UnknownType(name.to_owned()));
+                ty.to_owned()
+            }
+            Type::Function { params, ret } => Type::Function {
+                params: params
+                    .iter()
+                    .map(|param| self.declared_type(param, declaring))
+                    .collect(),
+                ret: Box::new(self.declared_type(ret, declaring)),
+            },
+            Type::Range(elem) => Type::Range(Box::new(self.declared_type(elem, declaring))),
+            Type::Array(elem) => Type::Array(Box::new(self.declared_type(elem, declaring))),
+            Type::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|element| self.declared_type(element, declaring))
+                    .collect(),
+            ),
+            _ => ty.to_owned(),
+        }
+    }
+
+    /// Constrains the value of an annotated declaration to the annotated type.
+    fn solve_annotation(&mut self, annotation: &Option<Type>, value: &mut Expression) {
+        if let Some(annotation) = annotation {
+            let annotation = self.declared_type(annotation, None);
+            self.unify(&annotation, &value.ty);
+        }
+    }
+
+    fn constructor(&self, name: &str) -> Option<(String, Vec<Type>)> {
+        self.enums.iter().find_map(|(enum_name, variants)| {
+            variants
+                .iter()
+                .find(|(variant, _)| variant == name)
+                .map(|(_, fields)| (enum_name.to_owned(), fields.to_owned()))
+        })
+    }
+
+    /// Unifies the pattern with the type of the value it is matched against and binds its names.
+    pub fn solve_pattern(&mut self, pattern: &mut Pattern, expected: &Type) {
+        if let Pattern::Binding(name) = pattern {
+            if self.constructor(name).is_some() {
+                *pattern = Pattern::Constructor {
+                    name: name.to_owned(),
+                    args: vec![],
+                };
+            }
+        }
+
+        match pattern {
+            Pattern::Wildcard => {}
+            Pattern::Binding(name) => {
+                self.symbol_table.insert(name.to_owned(), expected.to_owned().into());
+            }
+            Pattern::Integer(_) => self.unify(&Type::Integer, expected),
+            Pattern::Constructor { name, args } => match self.constructor(name) {
+                Some((enum_name, fields)) => {
+                    self.unify(&Type::Enum(enum_name), expected);
+                    if fields.len() != args.len() {
+                        self.errors.push(TypeError::ArityMismatch {
+                            expected: fields.len(),
+                            found: args.len(),
+                        });
+                        return;
+                    }
+                    for (arg, field) in args.iter_mut().zip(&fields) {
+                        self.solve_pattern(arg, field);
+                    }
+                }
+                None => self.errors.push(TypeError::UnknownConstructor(name.to_owned())),
+            },
+            Pattern::Tuple(elements) => {
+                let element_types = match self.shallow_resolve(expected) {
+                    Type::Tuple(element_types) => {
+                        if element_types.len() != elements.len() {
+                            self.errors.push(TypeError::TupleLengthMismatch {
+                                expected: element_types.len(),
+                                found: elements.len(),
+                            });
+                            // still bind the names in there, if only to unknown types
+                            elements.iter().map(|_| self.make_var_type()).collect()
+                        } else {
+                            element_types
+                        }
+                    }
+                    found => {
+                        let element_types: Vec<Type> =
+                            elements.iter().map(|_| self.make_var_type()).collect();
+                        self.unify(&found, &Type::Tuple(element_types.to_owned()));
+                        element_types
+                    }
+                };
+                for (element, element_type) in elements.iter_mut().zip(&element_types) {
+                    self.solve_pattern(element, element_type);
+                }
+            }
+        }
+    }
+
+    /// Checks the solved block, e.g. that matches are exhaustive
+    /// and that `break` and `continue` only appear inside of loops.
+    pub fn check_stmt_block(&self, block: &StatementBlock) -> Vec<TypeError> {
+        let mut checker = Checker {
+            solver: self,
+            in_loop: false,
+            errors: vec![],
+        };
+        for stmt in &block.stmts {
+            // `pub` is fine here, only the items of nested blocks cannot be exported
+            match &stmt.stmt {
+                Stmt::Public(item) => checker.visit_stmt(item),
+                _ => checker.visit_stmt(stmt),
+            }
+        }
+        checker.errors
+    }
+
+    /// Reports arms that can never be reached and the values no arm covers.
+    fn check_match(&self, arms: &[(Pattern, StatementBlock)]) -> Vec<TypeError> {
+        let mut errors = vec![];
+        let mut matrix = vec![];
+        for (pattern, _) in arms {
+            let row = vec![pattern.to_owned()];
+            if uncovered(&self.enums, &matrix, &row).is_empty() {
+                errors.push(TypeError::UnreachablePattern(pattern.to_owned()));
+            }
+            matrix.push(row);
+        }
+
+        let missing: Vec<Pattern> = uncovered(&self.enums, &matrix, &[Pattern::Wildcard])
+            .into_iter()
+            .map(|mut witness| witness.remove(0))
+            .collect();
+        if !missing.is_empty() {
+            errors.push(TypeError::NonExhaustiveMatch { missing });
+        }
+
+        errors
+    }
+
     pub fn solve_expr_recursive(&mut self, e: &mut Expression) {
         use Expr::*;
-        match e {
-            Expression {
-                ty: Type::Variable(n),
-                expr,
-            } => match expr {
-                Binop { lhs, rhs, .. } => {
-                    self.solve_expr_recursive(lhs);
-                    self.solve_expr_recursive(rhs);
-                    if (*lhs).ty == (*rhs).ty {
-                        self.constraints.insert(*n, lhs.ty.to_owned());
-                        e.ty = lhs.ty.to_owned();
-                    }
-                }
-                Name(name) => {
-                    let ty = self.symbol_table.get(name).unwrap().to_owned();
-                    self.constraints.insert(*n, ty.to_owned());
-                    e.ty = ty;
+        let ty = match &mut e.expr {
+            Binop { op, lhs, rhs } => {
+                self.solve_expr_recursive(lhs);
+                self.solve_expr_recursive(rhs);
+                match op {
+                    // assigning takes a value of whatever type the place holds
+                    Operator::Equals => {
+                        self.unify(&lhs.ty, &rhs.ty);
+                        lhs.ty.to_owned()
+                    }
+                    // arithmetic and ordering only apply to numbers
+                    _ => {
+                        self.unify(&Type::Integer, &lhs.ty);
+                        self.unify(&Type::Integer, &rhs.ty);
+                        match op {
+                            Operator::Greater | Operator::Less => Type::Bool,
+                            _ => Type::Integer,
+                        }
+                    }
+                }
+            }
+            And { lhs, rhs } | Or { lhs, rhs } => {
+                self.solve_expr_recursive(lhs);
+                self.solve_expr_recursive(rhs);
+                self.unify(&Type::Bool, &lhs.ty);
+                self.unify(&Type::Bool, &rhs.ty);
+                Type::Bool
+            }
+            Let { name, value, body } => {
+                self.solve_expr_recursive(value);
+                let outer_scope = self.symbol_table.clone();
+                self.symbol_table.insert(name.to_owned(), value.ty.to_owned().into());
+                self.solve_expr_recursive(body);
+                self.symbol_table = outer_scope;
+                body.ty.to_owned()
+            }
+            Name(name) => match self.symbol_table.get(name).cloned() {
+                Some(scheme) => self.instantiate(&scheme),
+                None => {
+                    self.errors.push(self.unknown_name(name));
+                    e.ty.to_owned()
                 }
-                _ => {}
             },
-            _ => {}
+            Integer(_) => Type::Integer,
+            Range { start, end } => {
+                self.solve_expr_recursive(start);
+                self.solve_expr_recursive(end);
+                self.unify(&Type::Integer, &start.ty);
+                self.unify(&start.ty, &end.ty);
+                Type::Range(Box::new(start.ty.to_owned()))
+            }
+            FunctionApplication { func, args } => {
+                self.solve_expr_recursive(func);
+                for arg in args.iter_mut() {
+                    self.solve_expr_recursive(arg);
+                }
+
+                let applied = Type::Function {
+                    params: args.iter().map(|arg| arg.ty.to_owned()).collect(),
+                    ret: Box::new(e.ty.to_owned()),
+                };
+                self.unify(&func.ty, &applied);
+                e.ty.to_owned()
+            }
+            Record { name, fields } => {
+                for (_, value) in fields.iter_mut() {
+                    self.solve_expr_recursive(value);
+                }
+
+                match self.structs.get(name).cloned() {
+                    Some(declared) => {
+                        let ty = Type::Struct(name.to_owned());
+                        for (field, field_ty) in &declared {
+                            match fields.iter().find(|(init, _)| init == field) {
+                                Some((_, value)) => self.unify(field_ty, &value.ty),
+                                None => self.errors.push(TypeError::MissingField {
+                                    ty: ty.to_owned(),
+                                    field: field.to_owned(),
+                                }),
+                            }
+                        }
+                        for (init, _) in fields.iter() {
+                            if !declared.iter().any(|(field, _)| field == init) {
+                                self.errors.push(TypeError::UnknownField {
+                                    ty: ty.to_owned(),
+                                    field: init.to_owned(),
+                                });
+                            }
+                        }
+                        ty
+                    }
+                    None => {
+                        self.errors.push(TypeError::UnknownType(name.to_owned()));
+                        e.ty.to_owned()
+                    }
+                }
+            }
+            FieldAccess { base, field } => {
+                self.solve_expr_recursive(base);
+                match self.shallow_resolve(&base.ty) {
+                    // not known yet, the rest of the program may tell
+                    Type::Variable(_) => {
+                        self.pending_fields.push(PendingField {
+                            base: base.ty.to_owned(),
+                            field: field.to_owned(),
+                            ty: e.ty.to_owned(),
+                        });
+                        e.ty.to_owned()
+                    }
+                    ty => self.field_type(ty, field).unwrap_or_else(|| e.ty.to_owned()),
+                }
+            }
+            Array(elements) => {
+                for element in elements.iter_mut() {
+                    self.solve_expr_recursive(element);
+                    if let Type::Array(elem) = &e.ty {
+                        self.unify(elem, &element.ty);
+                    }
+                }
+                e.ty.to_owned()
+            }
+            Index { base, index } => {
+                self.solve_expr_recursive(base);
+                self.solve_expr_recursive(index);
+                self.unify(&Type::Integer, &index.ty);
+                self.unify(&Type::Array(Box::new(e.ty.to_owned())), &base.ty);
+                e.ty.to_owned()
+            }
+            Slice { base, start, end } => {
+                self.solve_expr_recursive(base);
+                self.solve_expr_recursive(start);
+                self.solve_expr_recursive(end);
+                self.unify(&Type::Integer, &start.ty);
+                self.unify(&Type::Integer, &end.ty);
+                self.unify(&e.ty, &base.ty);
+                e.ty.to_owned()
+            }
+            Tuple(elements) => Type::Tuple(
+                elements
+                    .iter_mut()
+                    .map(|element| {
+                        self.solve_expr_recursive(element);
+                        element.ty.to_owned()
+                    })
+                    .collect(),
+            ),
+            Lambda { params, body, .. } => {
+                let outer_scope = self.symbol_table.clone();
+
+                if let Type::Function {
+                    params: param_types,
+                    ret,
+                } = self.shallow_resolve(&e.ty)
+                {
+                    for (param, param_type) in params.iter().zip(param_types) {
+                        self.symbol_table.insert(param.to_owned(), param_type.into());
+                    }
+                    self.solve_expr_recursive(body);
+                    self.unify(&ret, &body.ty);
+                }
+
+                self.symbol_table = outer_scope;
+                e.ty.to_owned()
+            }
+        };
+
+        self.unify(&e.ty, &ty);
+    }
+}
+
+/// Gives every function definition a function type of its arity.
+struct FunctionTypes<'s>(&'s mut TypeSolver);
+
+impl VisitMut for FunctionTypes<'_> {
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        if let Stmt::FunctionDef { params, ty, .. } = &mut stmt.stmt {
+            *ty = self.0.make_function_type(params.len());
         }
+        walk_stmt_mut(self, stmt)
     }
 
-    pub fn shallow_expr_iterator_from_stmt_block<'a>(
-        &mut self,
-        block: &'a mut StatementBlock,
-    ) -> impl IntoIterator<Item = &'a mut Expression> {
-        let mut out = vec![];
-        for stmt in &mut block.stmts {
-            out.extend(self.shallow_expr_iterator_from_stmt(stmt));
+    // function definitions only occur in statements
+    fn visit_expr_mut(&mut self, _expr: &mut Expression) {}
+}
+
+/// Emplaces type variables in the expressions of statements, which the solver descends into
+/// by itself.
+struct StatementExprs<'s>(&'s mut TypeSolver);
+
+impl VisitMut for StatementExprs<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expression) {
+        self.0.emplace_type_vars_in_exprs(expr)
+    }
+}
+
+/// Replaces every type in a tree with what the solver has found out about it.
+pub struct ResolvedTypes<'s>(pub &'s TypeSolver);
+
+impl Fold for ResolvedTypes<'_> {
+    fn fold_type(&mut self, ty: Type) -> Type {
+        self.0.resolve(&ty)
+    }
+}
+
+/// Whether `expr` is a syntactic value, one evaluating without calling anything or making
+/// a container that could be stored into. Only the types of those are generalized, as
+/// `let xs = []` could otherwise have elements of one type stored and of another read.
+fn is_value(expr: &Expression) -> bool {
+    match &expr.expr {
+        Expr::Lambda { .. } | Expr::Name(_) | Expr::Integer(_) => true,
+        Expr::Tuple(elements) => elements.iter().all(is_value),
+        _ => false,
+    }
+}
+
+/// Gathers the names assigned to in a tree, including those of `xs[i] = v` and `p.x = v`.
+#[derive(Default)]
+struct Assigned(BTreeSet<String>);
+
+impl Visit<'_> for Assigned {
+    fn visit_expr(&mut self, expr: &Expression) {
+        if let Expr::Binop {
+            op: Operator::Equals,
+            lhs,
+            ..
+        } = &expr.expr
+        {
+            let mut target = lhs;
+            loop {
+                match &target.expr {
+                    Expr::Name(name) => {
+                        self.0.insert(name.to_owned());
+                        break;
+                    }
+                    Expr::Index { base, .. }
+                    | Expr::Slice { base, .. }
+                    | Expr::FieldAccess { base, .. } => target = base,
+                    _ => break,
+                }
+            }
         }
-        out.into_iter()
+        walk_expr(self, expr)
     }
+}
 
-    pub fn shallow_expr_iterator_from_stmt<'a>(
-        &mut self,
-        stmt: &'a mut Statement,
-    ) -> impl IntoIterator<Item = &'a mut Expression> {
-        match &mut stmt.stmt {
-            crate::cst::Stmt::NameDeclaration { value, .. } => vec![value].into_iter(),
-            crate::cst::Stmt::Expression(expr) => vec![expr].into_iter(),
-            crate::cst::Stmt::While { pred, body } => {
-                let mut out = vec![pred];
-                out.extend(self.shallow_expr_iterator_from_stmt_block(body));
-                out.into_iter()
+/// Whether running `block` ends in a `return` on every path through it. Loops may run no
+/// times at all, so only `if`s with an `else` and matches can return on every path of theirs.
+fn always_returns(block: &StatementBlock) -> bool {
+    block.stmts.iter().any(|stmt| match &stmt.stmt {
+        Stmt::Return(_) => true,
+        Stmt::If {
+            body,
+            else_body: Some(else_body),
+            ..
+        } => always_returns(body) && always_returns(else_body),
+        // a match missing arms is reported as such, the arms it has are what runs
+        Stmt::Match { arms, .. } => arms.iter().all(|(_, body)| always_returns(body)),
+        _ => false,
+    })
+}
+
+/// `ty` with the variables that `vars` maps replaced by what they map to.
+fn substituted(ty: &Type, vars: &BTreeMap<u128, Type>) -> Type {
+    match ty {
+        Type::Variable(var) => vars.get(var).cloned().unwrap_or_else(|| ty.to_owned()),
+        Type::Function { params, ret } => Type::Function {
+            params: params.iter().map(|param| substituted(param, vars)).collect(),
+            ret: Box::new(substituted(ret, vars)),
+        },
+        Type::Range(elem) => Type::Range(Box::new(substituted(elem, vars))),
+        Type::Array(elem) => Type::Array(Box::new(substituted(elem, vars))),
+        Type::Tuple(elements) => {
+            Type::Tuple(elements.iter().map(|element| substituted(element, vars)).collect())
+        }
+        Type::Integer | Type::Bool | Type::Struct(_) | Type::Enum(_) | Type::Module(_) => {
+            ty.to_owned()
+        }
+    }
+}
+
+fn free_variables(ty: &Type, vars: &mut BTreeSet<u128>) {
+    match ty {
+        Type::Variable(var) => {
+            vars.insert(*var);
+        }
+        Type::Function { params, ret } => {
+            for param in params {
+                free_variables(param, vars);
+            }
+            free_variables(ret, vars);
+        }
+        Type::Range(elem) | Type::Array(elem) => free_variables(elem, vars),
+        Type::Tuple(elements) => {
+            for element in elements {
+                free_variables(element, vars);
+            }
+        }
+        Type::Integer | Type::Bool | Type::Struct(_) | Type::Enum(_) | Type::Module(_) => {}
+    }
+}
+
+/// Gathers the types of the declarations of a tree and the type variables found anywhere in it.
+#[derive(Default)]
+struct Unresolved {
+    declarations: Vec<(String, Type)>,
+    variables: BTreeSet<u128>,
+}
+
+impl Visit<'_> for Unresolved {
+    fn visit_stmt(&mut self, stmt: &Statement) {
+        let declaration = match &stmt.stmt {
+            Stmt::NameDeclaration { name, value, .. } => Some((name.to_owned(), &value.ty)),
+            Stmt::Destructure { pattern, value, .. } => Some((pattern.to_string(), &value.ty)),
+            Stmt::FunctionDef { name, ty, .. } => Some((name.to_owned(), ty)),
+            _ => None,
+        };
+        if let Some((name, ty)) = declaration.filter(|(_, ty)| !ty.is_complete()) {
+            self.declarations.push((name, ty.to_owned()));
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        free_variables(ty, &mut self.variables);
+    }
+}
+
+struct Checker<'s> {
+    solver: &'s TypeSolver,
+    in_loop: bool,
+    errors: Vec<TypeError>,
+}
+
+impl Checker<'_> {
+    fn visit_body(&mut self, body: &StatementBlock, in_loop: bool) {
+        let outer = std::mem::replace(&mut self.in_loop, in_loop);
+        self.visit_block(body);
+        self.in_loop = outer;
+    }
+}
+
+impl Visit<'_> for Checker<'_> {
+    fn visit_stmt(&mut self, stmt: &Statement) {
+        match &stmt.stmt {
+            Stmt::Public(_) => {
+                self.errors.push(TypeError::MisplacedPub);
+                walk_stmt(self, stmt);
+            }
+            Stmt::Destructure { pattern, .. } => {
+                // unlike a match there is no other arm to fall back to
+                let missing: Vec<Pattern> = uncovered(
+                    &self.solver.enums,
+                    &[vec![pattern.to_owned()]],
+                    &[Pattern::Wildcard],
+                )
+                .into_iter()
+                .map(|mut witness| witness.remove(0))
+                .collect();
+                if !missing.is_empty() {
+                    self.errors.push(TypeError::RefutablePattern { missing });
+                }
+            }
+            Stmt::While { body, .. } | Stmt::For { body, .. } => self.visit_body(body, true),
+            Stmt::If { .. } => walk_stmt(self, stmt),
+            Stmt::Match { arms, .. } => {
+                self.errors.extend(self.solver.check_match(arms));
+                walk_stmt(self, stmt);
+            }
+            Stmt::Break if !self.in_loop => self.errors.push(TypeError::BreakOutsideLoop),
+            Stmt::Continue if !self.in_loop => self.errors.push(TypeError::ContinueOutsideLoop),
+            // a loop around the definition does not make its body part of the loop
+            Stmt::FunctionDef { body, .. } => self.visit_body(body, false),
+            _ => {}
+        }
+    }
+
+    // statements never nest inside of expressions
+    fn visit_expr(&mut self, _expr: &Expression) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modules::parse_source;
+
+    use super::*;
+
+    /// Solves and checks `modules`, given by name and source, in order, each seeing the ones
+    /// before it as `main` does. Gives back the solver of the last one with its diagnostics.
+    fn solve_modules(modules: &[(&str, &str)]) -> (TypeSolver, Vec<String>) {
+        let mut scopes = BTreeMap::new();
+        let mut counter = 0;
+        let mut solved = (TypeSolver::default(), vec![]);
+        for (name, source) in modules {
+            let mut block = parse_source(source).unwrap();
+            let mut solver = TypeSolver {
+                modules: scopes.to_owned(),
+                counter,
+                ..Default::default()
+            };
+            for stmt in &mut block.stmts {
+                solver.emplace_type_vars_in_stmt(stmt)
             }
+            solver.solve(&mut block);
+            let resolved = ResolvedTypes(&solver).fold_block(block.to_owned());
+
+            let errors = solver
+                .errors
+                .iter()
+                .cloned()
+                .chain(solver.check_stmt_block(&resolved))
+                .chain(solver.unresolved(&resolved))
+                .map(|err| err.to_string())
+                .collect();
+            scopes.insert(name.to_string(), solver.scope(&block));
+            counter = solver.counter;
+            solved = (solver, errors);
         }
+        solved
+    }
+
+    /// Every diagnostic of the last of `modules`, see [`solve_modules`].
+    fn module_diagnostics(modules: &[(&str, &str)]) -> Vec<String> {
+        solve_modules(modules).1
+    }
+
+    /// Solves and checks `source` as a module of its own, giving back every diagnostic.
+    fn diagnostics(source: &str) -> Vec<String> {
+        module_diagnostics(&[("main", source)])
+    }
+
+    /// The type of the top-level `name` in `source`, which has to solve without errors. The
+    /// variables it is generic over are written `'a`, `'b`, ..., those it is not are `?`.
+    fn type_of(source: &str, name: &str) -> String {
+        let (solver, errors) = solve_modules(&[("main", source)]);
+        assert_eq!(errors, Vec::<String>::new());
+
+        let scheme = &solver.symbol_table[name];
+        let ty = solver.resolve(&scheme.ty);
+        let mut vars = BTreeSet::new();
+        free_variables(&ty, &mut vars);
+        let mut generic = ('a'..='z').map(|var| Type::Struct(format!("'{var}")));
+        let names = vars
+            .into_iter()
+            .map(|var| match scheme.vars.contains(&var) {
+                true => (var, generic.next().unwrap()),
+                false => (var, Type::Struct("?".to_owned())),
+            })
+            .collect();
+        substituted(&ty, &names).to_string()
+    }
+
+    const SHAPES: &str = "enum Shape: Circle(Int) | Rect(Int, Int) | Empty\n\
+                          let s = Rect(1, 2)\n";
+
+    #[test]
+    fn exhaustive_match() {
+        let source = format!(
+            "{SHAPES}match s:\n\
+             \x20   Circle(r):\n\
+             \x20       let a = r\n\
+             \x20   Rect(w, _):\n\
+             \x20       let b = w\n\
+             \x20   Empty:\n\
+             \x20       let c = 0\n"
+        );
+        assert_eq!(diagnostics(&source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn non_exhaustive_match() {
+        let source = format!(
+            "{SHAPES}match s:\n\
+             \x20   Circle(r):\n\
+             \x20       let a = r\n\
+             \x20   Empty:\n\
+             \x20       let c = 0\n"
+        );
+        assert_eq!(diagnostics(&source), ["non-exhaustive match, missing Rect(_, _)"]);
+    }
+
+    #[test]
+    fn unreachable_arm() {
+        let source = format!(
+            "{SHAPES}match s:\n\
+             \x20   _:\n\
+             \x20       let a = 0\n\
+             \x20   Empty:\n\
+             \x20       let c = 0\n"
+        );
+        assert_eq!(diagnostics(&source), ["unreachable pattern `Empty`"]);
+    }
+
+    #[test]
+    fn refutable_let() {
+        let source = format!("{SHAPES}let Circle(r) = s\n");
+        assert_eq!(diagnostics(&source), ["refutable pattern in `let`, missing Rect(_, _), Empty"]);
+    }
+
+    #[test]
+    fn irrefutable_let() {
+        assert_eq!(diagnostics("let (a, (b, _)) = (1, (2, 3))\n"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn tuple_length_mismatch() {
+        assert_eq!(
+            diagnostics("let (a, b) = (1, 2, 3)\n"),
+            ["length mismatch: expected a tuple of 3 elements, found 2"]
+        );
+    }
+
+    const UTIL: (&str, &str) = ("foo.util", "pub let k = 1\npub fn double(x):\n    return x * 2\n");
+
+    #[test]
+    fn import_binds_the_last_segment() {
+        let main = "import foo.util\nlet a = util.k + util.double(2)\n";
+        assert_eq!(module_diagnostics(&[UTIL, ("main", main)]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn qualified_module_access() {
+        // what cannot be inferred without `foo` is reported after it
+        let main = "import foo.util\nlet a = foo.util.k\n";
+        assert_eq!(
+            module_diagnostics(&[UTIL, ("main", main)])[0],
+            "unknown name `foo`, `import foo.util` binds the module as `util`"
+        );
+    }
+
+    #[test]
+    fn imports_are_scoped() {
+        let main = "fn f():\n    import foo.util\n    return util.k\nlet a = util.k\n";
+        assert_eq!(module_diagnostics(&[UTIL, ("main", main)])[0], "unknown name `util`");
+    }
+
+    #[test]
+    fn from_import() {
+        let main = "from foo.util import double\nlet a = double 1\n";
+        assert_eq!(module_diagnostics(&[UTIL, ("main", main)]), Vec::<String>::new());
+        let main = "from foo.util import triple\n";
+        assert_eq!(
+            module_diagnostics(&[UTIL, ("main", main)]),
+            ["module `foo.util` has no item `triple`"]
+        );
+    }
+
+    #[test]
+    fn private_items() {
+        let lib = ("lib", "let hidden = 1\npub let shown = hidden\n");
+        let main = "import lib\nlet a = lib.shown + lib.hidden\n";
+        assert_eq!(
+            module_diagnostics(&[lib, ("main", main)]),
+            ["`hidden` is private to module `lib`"]
+        );
+    }
+
+    #[test]
+    fn unknown_module() {
+        assert_eq!(diagnostics("import nope\n"), ["unknown module `nope`"]);
+    }
+
+    #[test]
+    fn conditions_are_bools() {
+        let source = "if 1 < 2:\n    let a = 1\nwhile 1 > 2:\n    let b = 1\n";
+        assert_eq!(diagnostics(source), Vec::<String>::new());
+        assert_eq!(
+            diagnostics("if 1:\n    let a = 1\n"),
+            ["type mismatch: expected Bool, found Integer"]
+        );
+        assert_eq!(
+            diagnostics("while 0..2:\n    let a = 1\n"),
+            ["type mismatch: expected Bool, found Range<Integer>"]
+        );
+        assert_eq!(
+            diagnostics("if 1 < 2:\n    let a = 1\nelif 3:\n    let b = 1\n"),
+            ["type mismatch: expected Bool, found Integer"]
+        );
+    }
+
+    #[test]
+    fn conditions_constrain_their_variables() {
+        // `x` is a `Bool` for being tested, so it cannot be given a number
+        let source = "fn f(x):\n    if x:\n        return 1\n    return 2\nlet a = f 3\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Bool, found Integer"]);
+        let source = "fn f(x):\n    if x:\n        return 1\n    return 2\nlet a = f (1 < 2)\n";
+        assert_eq!(diagnostics(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn falling_off_the_end_returns_unit() {
+        let source = "fn f(x):\n    let y = x\nlet a = f 1 + 1\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found ()"]);
+        let source = "fn f(x):\n    if x < 1:\n        return 1\nlet a = f 1\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found ()"]);
+        let source = "fn f(x):\n    while x < 1:\n        return 1\nlet a = f 1\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found ()"]);
+        let source = "fn f(x):\n    let y = x\nlet a: () = f 1\n";
+        assert_eq!(diagnostics(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn returning_on_every_path() {
+        let source = "fn f(x):\n\
+                      \x20   if x < 1:\n\
+                      \x20       return 1\n\
+                      \x20   else:\n\
+                      \x20       return 2\n\
+                      let a = f 1 + 1\n";
+        assert_eq!(diagnostics(source), Vec::<String>::new());
+        let source = format!(
+            "{SHAPES}fn area(s):\n\
+             \x20   match s:\n\
+             \x20       Circle(r):\n\
+             \x20           return r\n\
+             \x20       Rect(w, h):\n\
+             \x20           return w * h\n\
+             \x20       Empty:\n\
+             \x20           return 0\n\
+             let a = area s + 1\n"
+        );
+        assert_eq!(diagnostics(&source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn arithmetic_on_numbers() {
+        assert_eq!(diagnostics("let a = 1 + 2 * 3 - 4 % 5\n"), Vec::<String>::new());
+        assert_eq!(
+            diagnostics("let a = (1 < 2) + (2 < 3)\n"),
+            [
+                "type mismatch: expected Integer, found Bool",
+                "type mismatch: expected Integer, found Bool"
+            ]
+        );
+        assert_eq!(
+            diagnostics("let a = [1] * 2\n"),
+            ["type mismatch: expected Integer, found [Integer]"]
+        );
+    }
+
+    #[test]
+    fn ordering_numbers() {
+        assert_eq!(diagnostics("let a = 1 < 2\nlet b: Bool = 2 > 1\n"), Vec::<String>::new());
+        assert_eq!(
+            diagnostics("let a = (1 < 2) < (2 < 3)\n"),
+            [
+                "type mismatch: expected Integer, found Bool",
+                "type mismatch: expected Integer, found Bool"
+            ]
+        );
+        assert_eq!(
+            diagnostics("let a = 1 < 2\nlet b = a + 1\n"),
+            ["type mismatch: expected Integer, found Bool"]
+        );
+    }
+
+    #[test]
+    fn operands_are_inferred_as_numbers() {
+        // `x` is only ever added to, which makes it a number
+        let source = "fn inc(x):\n    return x + 1\nlet a = inc (1 < 2)\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found Bool"]);
+        let source = "fn lt(x, y):\n    return x < y\nlet a: Bool = lt(1, 2)\n";
+        assert_eq!(diagnostics(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn assignment_keeps_the_type() {
+        assert_eq!(diagnostics("let a = 1\na = 2\n"), Vec::<String>::new());
+        assert_eq!(
+            diagnostics("let a = 1\na = 1 < 2\n"),
+            ["type mismatch: expected Integer, found Bool"]
+        );
+    }
+
+    #[test]
+    fn assigned_lets_stay_monomorphic() {
+        let source = "let r = []\nr = [1 < 2]\nlet y = r[0] + 1\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found Bool"]);
+        let source = "let f = \\x -> x\nf = \\x -> x + 1\nlet a = f (1 < 2)\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found Bool"]);
+        let source = "fn f(x):\n    return x\nf = \\x -> x + 1\nlet a = f (1 < 2)\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found Bool"]);
+    }
+
+    #[test]
+    fn only_values_are_generalized() {
+        // `[]` makes an array that could be stored into, so it has one element type
+        let source = "let xs = []\nlet a = xs[0] + 1\nlet b = (1 < 2) and xs[0]\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Bool, found Integer"]);
+        // the result of a call is not a value either, while each call makes a new one
+        let empty = "fn empty():\n    return []\n";
+        let source = format!(
+            "{empty}let xs = empty()\n\
+             let a = xs[0] + 1\n\
+             let b = (1 < 2) and xs[0]\n"
+        );
+        assert_eq!(diagnostics(&source), ["type mismatch: expected Bool, found Integer"]);
+        let source = format!("{empty}let a = empty()[0] + 1\nlet b = (1 < 2) and empty()[0]\n");
+        assert_eq!(diagnostics(&source), Vec::<String>::new());
+        // names and tuples of values are values
+        let source = "let f = \\x -> x\n\
+                      let (g, n) = (f, 1)\n\
+                      let a = g 1 + n\n\
+                      let b = g (1 < 2) and g (2 < 1)\n";
+        assert_eq!(diagnostics(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unification() {
+        let source = "fn add(a, b):\n    return a + b\n";
+        assert_eq!(type_of(source, "add"), "(Integer, Integer) -> Integer");
+        let source = "let xs = [[1], []]\n";
+        assert_eq!(type_of(source, "xs"), "[[Integer]]");
+        let source = "let t = (1, 0..2, [1 < 2])\n";
+        assert_eq!(type_of(source, "t"), "(Integer, Range<Integer>, [Bool])");
+
+        assert_eq!(
+            diagnostics("let xs = [1, 1 < 2]\n"),
+            ["type mismatch: expected Integer, found Bool"]
+        );
+        assert_eq!(
+            diagnostics("let r = 0..(1 < 2)\n"),
+            ["type mismatch: expected Integer, found Bool"]
+        );
+        assert_eq!(
+            diagnostics("fn f(a, b):\n    return a\nlet x = f(1)\n")[0],
+            "arity mismatch: expected 2 arguments, found 1"
+        );
+        assert_eq!(
+            diagnostics("let (a, b) = (1, 1 < 2)\nlet c = a + b\n"),
+            ["type mismatch: expected Integer, found Bool"]
+        );
+    }
+
+    #[test]
+    fn records_and_fields() {
+        let source = "struct P: x: Int, y: Bool\nlet p = P { x: 1, y: 1 < 2 }\nlet a = p.x + 1\n";
+        assert_eq!(type_of(source, "a"), "Integer");
+        // a field is enough to tell the type of what it is accessed on
+        let source = "struct P: x: Int, y: Bool\nfn get(p: P):\n    return p.y\n";
+        assert_eq!(type_of(source, "get"), "(P) -> Bool");
+
+        let source = "struct P: x: Int, y: Bool\nlet p = P { x: 1 < 2, y: 1 < 2 }\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found Bool"]);
+        let source = "struct P: x: Int, y: Bool\nlet p = P { x: 1 }\n";
+        assert_eq!(diagnostics(source), ["missing field `y` in construction of P"]);
+        let source = "struct P: x: Int, y: Bool\nlet p = P { x: 1, y: 1 < 2 }\nlet z = p.z\n";
+        assert_eq!(diagnostics(source)[0], "P has no field `z`");
+    }
+
+    #[test]
+    fn constructors() {
+        let source = format!("{SHAPES}let c = Circle\n");
+        assert_eq!(type_of(&source, "c"), "(Integer) -> Shape");
+        let source = format!("{SHAPES}let c = Circle(1 < 2)\n");
+        assert_eq!(diagnostics(&source), ["type mismatch: expected Integer, found Bool"]);
+        let source = format!("{SHAPES}let Square(x) = s\n");
+        assert_eq!(diagnostics(&source)[0], "unknown constructor `Square`");
+        let source = format!("{SHAPES}let Circle(x, y) = s\n");
+        assert_eq!(diagnostics(&source)[0], "arity mismatch: expected 1 arguments, found 2");
+    }
+
+    #[test]
+    fn occurs_check() {
+        let errors = diagnostics("let f = \\x -> x x\n");
+        assert!(errors[0].starts_with("infinite type: "), "{errors:?}");
+        let errors = diagnostics("fn loop(x):\n    return loop\n");
+        assert!(errors[0].starts_with("infinite type: "), "{errors:?}");
+        // a variable may occur within what it is unified with after going through a binding
+        let source = "fn twice(f, x):\n    return f (f x)\n";
+        assert_eq!(type_of(source, "twice"), "(('a) -> 'a, 'a) -> 'a");
+    }
+
+    #[test]
+    fn let_polymorphism() {
+        let id = "fn id(x):\n    return x\n";
+        assert_eq!(type_of(id, "id"), "('a) -> 'a");
+        let source = format!("{id}let a = id 1 + 1\nlet b = id (1 < 2) and (1 < 2)\n");
+        assert_eq!(diagnostics(&source), Vec::<String>::new());
+        let source = "let id = \\x -> x\nlet a = (id 1, id (1 < 2))\n";
+        assert_eq!(type_of(source, "a"), "(Integer, Bool)");
+        // declarations in function bodies are generalized as well
+        let source = "fn f(y):\n    let id = \\x -> x\n    return (id y, id (1 < 2))\n";
+        assert_eq!(type_of(source, "f"), "('a) -> ('a, Bool)");
+    }
+
+    #[test]
+    fn parameters_are_monomorphic() {
+        // a parameter has one type throughout the body, whatever it is called with
+        let source = "fn both(f):\n    return (f 1, f (1 < 2))\n";
+        assert_eq!(diagnostics(source)[0], "type mismatch: expected Integer, found Bool");
+        let source = "let both = \\f -> (f 1, f (1 < 2))\n";
+        assert_eq!(diagnostics(source)[0], "type mismatch: expected Integer, found Bool");
+        // and so is a function within its own body
+        let source = "fn f(x):\n    let a = f 1\n    let b = f (1 < 2)\n    return x\n";
+        assert_eq!(diagnostics(source)[0], "type mismatch: expected Integer, found Bool");
+    }
+
+    #[test]
+    fn instantiation() {
+        let pair = "fn pair(x, y):\n    return (x, y)\n";
+        assert_eq!(type_of(pair, "pair"), "('a, 'b) -> ('a, 'b)");
+        let source = format!("{pair}let p = pair 1 (1 < 2)\nlet q = pair (1 < 2) 1\n");
+        assert_eq!(type_of(&source, "p"), "(Integer, Bool)");
+        assert_eq!(type_of(&source, "q"), "(Bool, Integer)");
+        // applying a generic function is no value, its result is not generalized
+        let source = "fn id(x):\n    return x\nlet f = id id\nlet a = f 1\n";
+        assert_eq!(type_of(source, "f"), "(Integer) -> Integer");
+        let source = "fn id(x):\n    return x\nlet f = id id\nlet a = f 1\nlet b = f (1 < 2)\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found Bool"]);
+    }
+
+    #[test]
+    fn annotations() {
+        assert_eq!(type_of("fn f(x: Int):\n    return x\n", "f"), "(Integer) -> Integer");
+        assert_eq!(type_of("fn f(x) -> Bool:\n    return x\n", "f"), "(Bool) -> Bool");
+        let source = "let (a, b): (Int, Bool) = (1, 1 < 2)\n";
+        assert_eq!(type_of(source, "b"), "Bool");
+
+        assert_eq!(
+            diagnostics("let x: Int = 1 < 2\n"),
+            ["type mismatch: expected Integer, found Bool"]
+        );
+        assert_eq!(
+            diagnostics("fn f(x: Int) -> Bool:\n    return x\n"),
+            ["type mismatch: expected Bool, found Integer"]
+        );
+        assert_eq!(diagnostics("let x: Foo = 1\n")[0], "unknown type `Foo`");
+    }
+
+    #[test]
+    fn captures() {
+        let source = "let y = 1\nlet f = \\x -> x + y\nlet a = f 2\n";
+        assert_eq!(type_of(source, "f"), "(Integer) -> Integer");
+        // a lambda shares the types of what it captures with the scope around it
+        let source = "fn f(y):\n    let g = \\x -> y\n    return g\n";
+        assert_eq!(type_of(source, "f"), "('a) -> ('b) -> 'a");
+        let source = "fn f(y):\n    let g = \\x -> y + x\n    return y\nlet a = f (1 < 2)\n";
+        assert_eq!(diagnostics(source), ["type mismatch: expected Integer, found Bool"]);
+        let source = "fn f(y):\n    let g = \\x -> y\n    let a = g 1 + 1\n    return g 2\n";
+        assert_eq!(type_of(source, "f"), "(Integer) -> Integer");
+        assert_eq!(diagnostics("let f = \\x -> x + y\n")[0], "unknown name `y`");
+    }
+
+    #[test]
+    fn modules_export_generic_schemes() {
+        let lib = ("lib", "pub fn id(x):\n    return x\n");
+        let main = "from lib import id\nlet a = id 1 + 1\nlet b = id (1 < 2) and (1 < 2)\n";
+        assert_eq!(module_diagnostics(&[lib, ("main", main)]), Vec::<String>::new());
+        let main = "import lib\nlet a = lib.id 1 + 1\nlet b = lib.id (1 < 2) and (1 < 2)\n";
+        assert_eq!(module_diagnostics(&[lib, ("main", main)]), Vec::<String>::new());
     }
 }
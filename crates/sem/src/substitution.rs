@@ -0,0 +1,101 @@
+//! What the solver knows about its type variables. Variables found equal are merged into one
+//! class with union-find, and a class learning its type gets bound to it, so that unifying
+//! two variables costs next to nothing however long the chains of them get.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ty::Type;
+
+#[derive(Debug, Clone, Default)]
+pub struct Substitution {
+    /// The variable each one was merged into, the representatives of classes have none.
+    parents: BTreeMap<u128, u128>,
+    /// An upper bound on the height of the tree below each representative.
+    ranks: BTreeMap<u128, u32>,
+    /// The types the classes are bound to, by representative. Never a bare variable.
+    bindings: BTreeMap<u128, Type>,
+    /// The representatives of the classes bound to types without unbound variables in them,
+    /// which nothing can change anymore.
+    ground: BTreeSet<u128>,
+}
+
+impl Substitution {
+    /// The representative of the class of `var`.
+    pub fn find(&self, var: u128) -> u128 {
+        let mut var = var;
+        while let Some(&parent) = self.parents.get(&var) {
+            var = parent;
+        }
+        var
+    }
+
+    /// Like [`Substitution::find`], pointing every variable on the way straight at the
+    /// representative so that the next lookups are shorter.
+    fn find_compressing(&mut self, var: u128) -> u128 {
+        let root = self.find(var);
+        let mut var = var;
+        while let Some(parent) = self.parents.insert(var, root) {
+            var = parent;
+        }
+        self.parents.remove(&root);
+        root
+    }
+
+    /// The type the class of `var` is bound to, if any.
+    pub fn binding(&self, var: u128) -> Option<&Type> {
+        self.bindings.get(&self.find(var))
+    }
+
+    /// Merges the classes of two unbound variables.
+    pub fn union(&mut self, a: u128, b: u128) {
+        let (a, b) = (self.find_compressing(a), self.find_compressing(b));
+        if a == b {
+            return;
+        }
+        debug_assert!(!self.bindings.contains_key(&a) && !self.bindings.contains_key(&b));
+
+        let (rank_a, rank_b) = (self.rank(a), self.rank(b));
+        let (root, child) = if rank_a < rank_b { (b, a) } else { (a, b) };
+        self.parents.insert(child, root);
+        if rank_a == rank_b {
+            self.ranks.insert(root, rank_a + 1);
+        }
+    }
+
+    /// Binds the class of the unbound variable `var` to `ty`, which must not contain it.
+    pub fn bind(&mut self, var: u128, ty: Type) {
+        debug_assert!(
+            !matches!(ty, Type::Variable(_)),
+            "variables are merged, not bound"
+        );
+        let root = self.find_compressing(var);
+        if self.holds_only_ground(&ty) {
+            self.ground.insert(root);
+        }
+        let previous = self.bindings.insert(root, ty);
+        debug_assert!(previous.is_none(), "T{root} is bound already");
+    }
+
+    /// Whether the class of `var` is bound to a type without unbound variables in it.
+    pub fn is_ground(&self, var: u128) -> bool {
+        self.ground.contains(&self.find(var))
+    }
+
+    /// Whether every variable in `ty` belongs to a ground class.
+    fn holds_only_ground(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Variable(var) => self.is_ground(*var),
+            Type::Function { params, ret } => {
+                params.iter().all(|param| self.holds_only_ground(param))
+                    && self.holds_only_ground(ret)
+            }
+            Type::Range(elem) | Type::Array(elem) => self.holds_only_ground(elem),
+            Type::Tuple(elements) => elements.iter().all(|element| self.holds_only_ground(element)),
+            Type::Integer | Type::Bool | Type::Struct(_) | Type::Enum(_) | Type::Module(_) => true,
+        }
+    }
+
+    fn rank(&self, var: u128) -> u32 {
+        self.ranks.get(&var).copied().unwrap_or_default()
+    }
+}
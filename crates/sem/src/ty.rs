@@ -1,8 +1,41 @@
-#[derive(Debug, Clone)]
+use std::{collections::BTreeSet, fmt::Display};
+
+use r#macro::Visitable;
+use serde::Serialize;
+
+use crate::cst::Pattern;
+
+#[derive(Debug, Clone, Serialize, Visitable)]
+#[visit(type)]
 pub enum Type {
     Variable(u128),
     Integer,
     Bool,
+    Function { params: Vec<Type>, ret: Box<Type> },
+    Range(Box<Type>),
+    Struct(String),
+    Enum(String),
+    Array(Box<Type>),
+    Tuple(Vec<Type>),
+    Module(String),
+}
+
+/// The type of a generalized declaration, its `vars` standing for whichever types each use
+/// of it needs, e.g. `(T1) -> T1` for a function returning its argument.
+#[derive(Debug, Clone, Default)]
+pub struct Scheme {
+    pub vars: BTreeSet<u128>,
+    pub ty: Type,
+}
+
+/// A type that is the same at every use, like those of parameters and of loop variables.
+impl From<Type> for Scheme {
+    fn from(ty: Type) -> Self {
+        Self {
+            vars: BTreeSet::new(),
+            ty,
+        }
+    }
 }
 
 pub trait Typed {
@@ -13,7 +46,10 @@ impl Typed for Type {
     fn is_complete(&self) -> bool {
         match self {
             Self::Variable(_) => false,
-            Self::Integer | Self::Bool => true,
+            Self::Integer | Self::Bool | Self::Struct(_) | Self::Enum(_) | Self::Module(_) => true,
+            Self::Function { params, ret } => params.iter().all(Typed::is_complete) && ret.is_complete(),
+            Self::Range(elem) | Self::Array(elem) => elem.is_complete(),
+            Self::Tuple(elements) => elements.iter().all(Typed::is_complete),
         }
     }
 }
@@ -22,6 +58,16 @@ impl PartialEq for Type {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Variable(l0), Self::Variable(r0)) => l0 == r0,
+            (
+                Self::Function { params: lparams, ret: lret },
+                Self::Function { params: rparams, ret: rret },
+            ) => lparams == rparams && lret == rret,
+            (Self::Range(l0), Self::Range(r0)) => l0 == r0,
+            (Self::Array(l0), Self::Array(r0)) => l0 == r0,
+            (Self::Tuple(l0), Self::Tuple(r0)) => l0 == r0,
+            (Self::Struct(l0), Self::Struct(r0)) => l0 == r0,
+            (Self::Enum(l0), Self::Enum(r0)) => l0 == r0,
+            (Self::Module(l0), Self::Module(r0)) => l0 == r0,
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
@@ -32,3 +78,114 @@ impl Default for Type {
         Type::Variable(0)
     }
 }
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Variable(var) => write!(f, "T{var}"),
+            Type::Integer => write!(f, "Integer"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Function { params, ret } => {
+                let params: Vec<String> = params.iter().map(ToString::to_string).collect();
+                write!(f, "({}) -> {ret}", params.join(", "))
+            }
+            Type::Range(elem) => write!(f, "Range<{elem}>"),
+            Type::Struct(name) | Type::Enum(name) => write!(f, "{name}"),
+            Type::Array(elem) => write!(f, "[{elem}]"),
+            Type::Module(name) => write!(f, "module {name}"),
+            Type::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            Type::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(ToString::to_string).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeError {
+    Mismatch { expected: Type, found: Type },
+    ArityMismatch { expected: usize, found: usize },
+    TupleLengthMismatch { expected: usize, found: usize },
+    ReturnOutsideFunction,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    UnknownType(String),
+    UnknownField { ty: Type, field: String },
+    MissingField { ty: Type, field: String },
+    UnknownConstructor(String),
+    NonExhaustiveMatch { missing: Vec<Pattern> },
+    UnreachablePattern(Pattern),
+    RefutablePattern { missing: Vec<Pattern> },
+    UnknownModule(String),
+    UnknownImport { module: String, name: String },
+    PrivateItem { module: String, name: String },
+    MisplacedPub,
+    InfiniteType { var: Type, ty: Type },
+    UnknownName(String),
+    /// A name used as the first segments of an imported module's path, which only binds the last.
+    QualifiedModule { name: String, module: String, binding: String },
+    UnresolvedField(String),
+    AmbiguousType { name: String, ty: Type },
+    UnresolvedVariables(Vec<Type>),
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeError::Mismatch { expected, found } => {
+                write!(f, "type mismatch: expected {expected}, found {found}")
+            }
+            TypeError::ArityMismatch { expected, found } => {
+                write!(f, "arity mismatch: expected {expected} arguments, found {found}")
+            }
+            TypeError::TupleLengthMismatch { expected, found } => {
+                write!(f, "length mismatch: expected a tuple of {expected} elements, found {found}")
+            }
+            TypeError::ReturnOutsideFunction => write!(f, "`return` outside of a function"),
+            TypeError::BreakOutsideLoop => write!(f, "`break` outside of a loop"),
+            TypeError::ContinueOutsideLoop => write!(f, "`continue` outside of a loop"),
+            TypeError::UnknownType(name) => write!(f, "unknown type `{name}`"),
+            TypeError::UnknownField { ty, field } => write!(f, "{ty} has no field `{field}`"),
+            TypeError::MissingField { ty, field } => {
+                write!(f, "missing field `{field}` in construction of {ty}")
+            }
+            TypeError::UnknownConstructor(name) => write!(f, "unknown constructor `{name}`"),
+            TypeError::NonExhaustiveMatch { missing } => {
+                let missing: Vec<String> = missing.iter().map(ToString::to_string).collect();
+                write!(f, "non-exhaustive match, missing {}", missing.join(", "))
+            }
+            TypeError::UnreachablePattern(pattern) => write!(f, "unreachable pattern `{pattern}`"),
+            TypeError::RefutablePattern { missing } => {
+                let missing: Vec<String> = missing.iter().map(ToString::to_string).collect();
+                write!(f, "refutable pattern in `let`, missing {}", missing.join(", "))
+            }
+            TypeError::UnknownModule(module) => write!(f, "unknown module `{module}`"),
+            TypeError::UnknownImport { module, name } => {
+                write!(f, "module `{module}` has no item `{name}`")
+            }
+            TypeError::PrivateItem { module, name } => {
+                write!(f, "`{name}` is private to module `{module}`")
+            }
+            TypeError::MisplacedPub => write!(f, "`pub` is only allowed on top-level items"),
+            TypeError::InfiniteType { var, ty } => {
+                write!(f, "infinite type: {var} would have to be {ty}")
+            }
+            TypeError::UnknownName(name) => write!(f, "unknown name `{name}`"),
+            TypeError::QualifiedModule { name, module, binding } => write!(
+                f,
+                "unknown name `{name}`, `import {module}` binds the module as `{binding}`"
+            ),
+            TypeError::UnresolvedField(field) => {
+                write!(f, "cannot infer the type field `{field}` is accessed on")
+            }
+            TypeError::AmbiguousType { name, ty } => {
+                write!(f, "cannot infer the type of `{name}`, only that it is {ty}")
+            }
+            TypeError::UnresolvedVariables(vars) => {
+                let vars: Vec<String> = vars.iter().map(ToString::to_string).collect();
+                write!(f, "cannot infer the type variables {}", vars.join(", "))
+            }
+        }
+    }
+}
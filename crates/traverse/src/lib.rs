@@ -0,0 +1,230 @@
+//! The runtime shared by the syntax trees deriving `Visitable`: the traits the derive
+//! implements, along with their implementations for the containers the trees are built from.
+//!
+//! The traits are generic over the visitor, so that each tree brings its own `Visit`,
+//! `VisitMut` and `Fold` with a method for every kind of node it has, while the way
+//! through a `Box`, an `Rc` or a `Vec` is written once for all of them.
+
+use std::rc::Rc;
+
+/// A part of a tree a visitor of type `V` can descend into, implemented with
+/// `#[derive(Visitable)]`. Nodes marked `#[visit(name)]` are handed to their `visit_*`
+/// method, everything else is walked straight away. The root of the tree names the nodes,
+/// and the visitors with a method for each are generated along with it:
+///
+/// ```
+/// use r#macro::Visitable;
+///
+/// #[derive(Visitable)]
+/// #[visit(list, nodes(item = "Item"))]
+/// pub struct List {
+///     pub items: Vec<Item>,
+/// }
+///
+/// #[derive(Visitable)]
+/// #[visit(item)]
+/// pub struct Item {
+///     pub name: String,
+/// }
+///
+/// struct Names(Vec<String>);
+///
+/// impl<'ast> visit::Visit<'ast> for Names {
+///     fn visit_item(&mut self, item: &'ast Item) {
+///         self.0.push(item.name.to_owned());
+///     }
+/// }
+///
+/// fn main() {
+///     let list = List {
+///         items: vec![Item { name: "a".to_owned() }, Item { name: "b".to_owned() }],
+///     };
+///     let mut names = Names(vec![]);
+///     visit::Visit::visit_list(&mut names, &list);
+///     assert_eq!(names.0, ["a", "b"]);
+/// }
+/// ```
+///
+/// A misspelled argument does not derive it:
+///
+/// ```compile_fail
+/// #[derive(r#macro::Visitable)]
+/// #[visit(item, visitor = "crate")]
+/// pub struct Item {
+///     pub name: String,
+/// }
+/// ```
+///
+/// Nor does a node listed without its type:
+///
+/// ```compile_fail
+/// #[derive(r#macro::Visitable)]
+/// #[visit(list, nodes(item))]
+/// pub struct List {
+///     pub items: Vec<String>,
+/// }
+/// ```
+///
+/// Nor a root without a method of its own:
+///
+/// ```compile_fail
+/// #[derive(r#macro::Visitable)]
+/// #[visit(nodes(item = "String"))]
+/// pub struct List {
+///     pub items: Vec<String>,
+/// }
+/// ```
+pub trait Visitable<'ast, V: ?Sized> {
+    fn accept(&'ast self, visitor: &mut V) {
+        self.walk(visitor)
+    }
+
+    /// Accepts the visitor on every child, in declaration order.
+    fn walk(&'ast self, visitor: &mut V);
+}
+
+/// The mutable counterpart of [`Visitable`].
+pub trait VisitableMut<V: ?Sized> {
+    fn accept_mut(&mut self, visitor: &mut V) {
+        self.walk_mut(visitor)
+    }
+
+    fn walk_mut(&mut self, visitor: &mut V);
+}
+
+/// The rebuilding counterpart of [`Visitable`].
+pub trait Foldable<F: ?Sized>: Sized {
+    fn fold_with(self, folder: &mut F) -> Self {
+        self.fold_children(folder)
+    }
+
+    fn fold_children(self, folder: &mut F) -> Self;
+}
+
+/// Implements the traversal traits for types without children, for any visitor.
+#[macro_export]
+macro_rules! leaves {
+    ($($leaf:ty),* $(,)?) => {
+        $(
+            impl<'ast, V: ?Sized> $crate::Visitable<'ast, V> for $leaf {
+                fn walk(&'ast self, _: &mut V) {}
+            }
+
+            impl<V: ?Sized> $crate::VisitableMut<V> for $leaf {
+                fn walk_mut(&mut self, _: &mut V) {}
+            }
+
+            impl<F: ?Sized> $crate::Foldable<F> for $leaf {
+                fn fold_children(self, _: &mut F) -> Self {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+leaves!(String, bool, usize, u32, u128);
+
+impl<'ast, V: ?Sized, T: Visitable<'ast, V>> Visitable<'ast, V> for Box<T> {
+    fn walk(&'ast self, visitor: &mut V) {
+        (**self).accept(visitor)
+    }
+}
+
+impl<V: ?Sized, T: VisitableMut<V>> VisitableMut<V> for Box<T> {
+    fn walk_mut(&mut self, visitor: &mut V) {
+        (**self).accept_mut(visitor)
+    }
+}
+
+impl<F: ?Sized, T: Foldable<F>> Foldable<F> for Box<T> {
+    fn fold_children(mut self, folder: &mut F) -> Self {
+        // moving out and back into the box keeps its allocation
+        *self = (*self).fold_with(folder);
+        self
+    }
+}
+
+impl<'ast, V: ?Sized, T: Visitable<'ast, V>> Visitable<'ast, V> for Rc<T> {
+    fn walk(&'ast self, visitor: &mut V) {
+        (**self).accept(visitor)
+    }
+}
+
+impl<V: ?Sized, T: VisitableMut<V> + Clone> VisitableMut<V> for Rc<T> {
+    fn walk_mut(&mut self, visitor: &mut V) {
+        // a node shared with another tree is copied before it is changed
+        Rc::make_mut(self).accept_mut(visitor)
+    }
+}
+
+impl<F: ?Sized, T: Foldable<F> + Clone> Foldable<F> for Rc<T> {
+    fn fold_children(self, folder: &mut F) -> Self {
+        // a node shared with another tree is copied, one owned by this tree alone is moved
+        Rc::new(Rc::unwrap_or_clone(self).fold_with(folder))
+    }
+}
+
+impl<'ast, V: ?Sized, T: Visitable<'ast, V>> Visitable<'ast, V> for Option<T> {
+    fn walk(&'ast self, visitor: &mut V) {
+        if let Some(inner) = self {
+            inner.accept(visitor);
+        }
+    }
+}
+
+impl<V: ?Sized, T: VisitableMut<V>> VisitableMut<V> for Option<T> {
+    fn walk_mut(&mut self, visitor: &mut V) {
+        if let Some(inner) = self {
+            inner.accept_mut(visitor);
+        }
+    }
+}
+
+impl<F: ?Sized, T: Foldable<F>> Foldable<F> for Option<T> {
+    fn fold_children(self, folder: &mut F) -> Self {
+        self.map(|inner| inner.fold_with(folder))
+    }
+}
+
+impl<'ast, V: ?Sized, T: Visitable<'ast, V>> Visitable<'ast, V> for Vec<T> {
+    fn walk(&'ast self, visitor: &mut V) {
+        for element in self {
+            element.accept(visitor);
+        }
+    }
+}
+
+impl<V: ?Sized, T: VisitableMut<V>> VisitableMut<V> for Vec<T> {
+    fn walk_mut(&mut self, visitor: &mut V) {
+        for element in self {
+            element.accept_mut(visitor);
+        }
+    }
+}
+
+impl<F: ?Sized, T: Foldable<F>> Foldable<F> for Vec<T> {
+    fn fold_children(self, folder: &mut F) -> Self {
+        self.into_iter().map(|element| element.fold_with(folder)).collect()
+    }
+}
+
+impl<'ast, V: ?Sized, A: Visitable<'ast, V>, B: Visitable<'ast, V>> Visitable<'ast, V> for (A, B) {
+    fn walk(&'ast self, visitor: &mut V) {
+        self.0.accept(visitor);
+        self.1.accept(visitor);
+    }
+}
+
+impl<V: ?Sized, A: VisitableMut<V>, B: VisitableMut<V>> VisitableMut<V> for (A, B) {
+    fn walk_mut(&mut self, visitor: &mut V) {
+        self.0.accept_mut(visitor);
+        self.1.accept_mut(visitor);
+    }
+}
+
+impl<F: ?Sized, A: Foldable<F>, B: Foldable<F>> Foldable<F> for (A, B) {
+    fn fold_children(self, folder: &mut F) -> Self {
+        (self.0.fold_with(folder), self.1.fold_with(folder))
+    }
+}
@@ -0,0 +1,67 @@
+mod printer;
+
+use std::{env, fs, process::ExitCode};
+
+use printer::format;
+
+const USAGE: &str = "usage: wbfmt [--check] FILE...";
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut files = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    }
+
+    let mut failed = false;
+    let mut unformatted = false;
+    for file in files {
+        let contents = match fs::read_to_string(&file) {
+            Ok(contents) => contents,
+            Err(err) => {
+                eprintln!("{file}: error: {err}");
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match format(&contents) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                let loc = err.loc();
+                eprintln!("{file}:{}:{}: error: {err}", loc.lineno, loc.col);
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == contents {
+            continue;
+        }
+
+        if check {
+            println!("{file}");
+            unformatted = true;
+        } else if let Err(err) = fs::write(&file, formatted) {
+            eprintln!("{file}: error: {err}");
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if unformatted {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
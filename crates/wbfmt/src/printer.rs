@@ -0,0 +1,118 @@
+use lex::{SourceSpan, TokenKind, TokenStream};
+use parse::{syntax::parse_lossless, Printer, SyntaxError};
+
+const INDENT: &str = "    ";
+
+/// Lines growing past this width are broken up, see [`Printer::width`].
+pub const MAX_WIDTH: usize = 100;
+
+struct Comment {
+    span: SourceSpan,
+    text: String,
+    /// Whether the comment sits on a line of its own rather than after some code.
+    own_line: bool,
+}
+
+/// Re-emits `source` in canonical form, keeping its comments and at most one blank line
+/// between consecutive statements.
+pub fn format(source: &str) -> Result<String, SyntaxError> {
+    let mut layout = Layout {
+        source,
+        comments: comments_of(source),
+        next_comment: 0,
+        out: String::new(),
+    };
+
+    let file = parse_lossless(source)?;
+    let mut printer = Printer::new(MAX_WIDTH);
+    printer.keep_groupings = false;
+    printer.block(file.ast());
+
+    // the comments and blank lines go in front of the statements they preceded
+    let printed = printer.output();
+    let mut cursor = 0;
+    for mapping in printer.source_map() {
+        layout.out.push_str(&printed[cursor..mapping.output]);
+        cursor = mapping.output;
+        layout.comments_before(mapping.source, mapping.indent);
+        if layout.follows_blank_line(mapping.source) {
+            layout.blank_line();
+        }
+    }
+    layout.out.push_str(&printed[cursor..]);
+    layout.comments_before(usize::MAX, 0);
+    Ok(layout.out)
+}
+
+fn comments_of(source: &str) -> Vec<Comment> {
+    TokenStream::new(source)
+        .filter_map(|token| match token.kind {
+            TokenKind::Comment(text) => {
+                let line_start = source[..token.span.0].rfind('\n').map_or(0, |i| i + 1);
+                Some(Comment {
+                    span: token.span,
+                    text: text.trim_end().to_owned(),
+                    own_line: source[line_start..token.span.0].trim().is_empty(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+struct Layout<'a> {
+    source: &'a str,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    out: String,
+}
+
+impl Layout<'_> {
+    /// Whether the source line above the one holding `offset` is blank, with nothing but
+    /// indentation in front of `offset` on its own line.
+    fn follows_blank_line(&self, offset: usize) -> bool {
+        let Some(line_start) = self.source[..offset].rfind('\n') else {
+            return false;
+        };
+        // statements after a `;` or a block header share the line of what came before them
+        if !self.source[line_start + 1..offset].trim().is_empty() {
+            return false;
+        }
+        let previous_start = self.source[..line_start].rfind('\n').map_or(0, |i| i + 1);
+        self.source[previous_start..line_start].trim().is_empty()
+    }
+
+    /// Separates what follows with a blank line, unless at the start of the file or a block.
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.out.ends_with(":\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Emits the comments found before `offset`: trailing ones go back onto the last line
+    /// printed, the others get a line of their own at the given indentation.
+    fn comments_before(&mut self, offset: usize, indent: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.0 >= offset {
+                break;
+            }
+            let Comment { span, text, own_line } = comment;
+            let (span, text, own_line) = (*span, text.to_owned(), *own_line);
+            self.next_comment += 1;
+
+            if !own_line && self.out.ends_with('\n') {
+                self.out.pop();
+                self.out.push_str("  ");
+                self.out.push_str(&text);
+                self.out.push('\n');
+                continue;
+            }
+            if self.follows_blank_line(span.0) {
+                self.blank_line();
+            }
+            self.out.push_str(&INDENT.repeat(indent));
+            self.out.push_str(&text);
+            self.out.push('\n');
+        }
+    }
+}